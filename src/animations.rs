@@ -0,0 +1,251 @@
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    render::texture::{ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor},
+    utils::HashMap,
+};
+use bevy_spritesheet_animation::{
+    animation::{AnimationDuration, AnimationId, AnimationRepeat},
+    library::SpritesheetLibrary,
+    spritesheet::Spritesheet,
+};
+use serde::Deserialize;
+
+use crate::ron_loader::RonAssetApp;
+
+/// Sprite sheets and the animations cut from them, described by `.animations.ron` manifests.
+pub struct AnimationsPlugin;
+impl Plugin for AnimationsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_ron_asset::<AnimationManifest>(&["animations.ron"]);
+        app.init_resource::<RegisteredSheets>();
+    }
+}
+
+/// States every animated character is expected to have.
+pub const IDLE: &str = "idle";
+pub const WALK: &str = "walk";
+pub const HURT: &str = "hurt";
+pub const DEATH: &str = "death";
+/// Played by the player whenever one of their weapons fires.
+pub const CAST: &str = "cast";
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    /// The nearest of the eight directions to `vector`, or `None` if it has no length.
+    pub fn from_vector(vector: Vec2) -> Option<Self> {
+        if vector == Vec2::ZERO {
+            return None;
+        }
+        // Eighths of a turn anticlockwise from +X, each centred on its direction.
+        let octant = (vector.to_angle() / std::f32::consts::FRAC_PI_4).round() as i32;
+        Some(match octant.rem_euclid(8) {
+            0 => Direction::Right,
+            1 => Direction::UpRight,
+            2 => Direction::Up,
+            3 => Direction::UpLeft,
+            4 => Direction::Left,
+            5 => Direction::DownLeft,
+            6 => Direction::Down,
+            _ => Direction::DownRight,
+        })
+    }
+
+    /// Used when a sheet has no row for a diagonal. Sheets are drawn side-on, so sideways
+    /// rows read better than facing straight up or down.
+    fn fallback(self) -> Self {
+        match self {
+            Direction::UpLeft | Direction::DownLeft => Direction::Left,
+            Direction::UpRight | Direction::DownRight => Direction::Right,
+            direction => direction,
+        }
+    }
+}
+
+/// A sprite sheet and the animation of each named state, loaded from a `.animations.ron` file.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct AnimationManifest {
+    pub texture: String,
+    /// Size in pixels of one frame of the sheet.
+    pub tile_size: (u32, u32),
+    pub columns: usize,
+    pub rows: usize,
+    pub states: HashMap<String, StateAnimations>,
+}
+
+#[derive(Deserialize, Debug)]
+pub enum StateAnimations {
+    /// Played whichever way the sprite is facing.
+    Single(AnimationDefinition),
+    /// Diagonals may be left out, falling back to the sideways animation.
+    Directional(HashMap<Direction, AnimationDefinition>),
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AnimationDefinition {
+    /// Clips played one after another.
+    pub stages: Vec<ClipDefinition>,
+    /// How many times the animation plays before stopping; loops forever if unset.
+    #[serde(default)]
+    pub cycles: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ClipDefinition {
+    /// Runs of frames played in order, each `(row, first column, end column)` with the end
+    /// column excluded.
+    pub frames: Vec<(usize, usize, usize)>,
+    /// Milliseconds for one play through all the frames.
+    #[serde(default)]
+    pub duration: Option<u32>,
+}
+
+impl AnimationDefinition {
+    fn register(&self, sheet: &Spritesheet, library: &mut SpritesheetLibrary) -> AnimationId {
+        let clips: Vec<_> = self
+            .stages
+            .iter()
+            .map(|stage| {
+                library.new_clip(|clip| {
+                    for &(row, start, end) in stage.frames.iter() {
+                        clip.push_frame_indices(sheet.row_partial(row, start..end));
+                    }
+                    if let Some(duration) = stage.duration {
+                        clip.set_default_duration(AnimationDuration::PerCycle(duration));
+                    }
+                })
+            })
+            .collect();
+        library.new_animation(|animation| {
+            for clip in clips.iter() {
+                animation.add_stage((*clip).into());
+            }
+            if let Some(cycles) = self.cycles {
+                animation.set_repeat(AnimationRepeat::Cycles(cycles));
+            }
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+enum StateIds {
+    Single(AnimationId),
+    Directional(HashMap<Direction, AnimationId>),
+}
+
+/// The animations of one manifest, registered in the [`SpritesheetLibrary`].
+#[derive(Clone, Debug, Default)]
+pub struct SpriteAnimations(HashMap<String, StateIds>);
+
+impl SpriteAnimations {
+    pub fn get(&self, state: &str, direction: Direction) -> Option<AnimationId> {
+        match self.0.get(state)? {
+            StateIds::Single(animation) => Some(*animation),
+            StateIds::Directional(animations) => animations
+                .get(&direction)
+                .or_else(|| animations.get(&direction.fallback()))
+                .copied(),
+        }
+    }
+}
+
+/// Everything needed to draw and animate a sprite from a manifest.
+#[derive(Clone)]
+pub struct AnimatedSheet {
+    pub texture: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    pub animations: SpriteAnimations,
+}
+
+#[derive(Resource, Default)]
+struct RegisteredSheets {
+    /// Keyed by the manifest's asset path.
+    sheets: HashMap<String, AnimatedSheet>,
+    /// Kept alive until they load.
+    loading: HashMap<String, Handle<AnimationManifest>>,
+}
+
+/// Looks up manifests by path, registering each one's animations the first time it's used.
+#[derive(SystemParam)]
+pub struct AnimatedSheets<'w> {
+    registered: ResMut<'w, RegisteredSheets>,
+    manifests: Res<'w, Assets<AnimationManifest>>,
+    library: ResMut<'w, SpritesheetLibrary>,
+    layouts: ResMut<'w, Assets<TextureAtlasLayout>>,
+    assets: Res<'w, AssetServer>,
+}
+
+impl AnimatedSheets<'_> {
+    /// Starts loading the manifest at `path` if it hasn't been, and returns its sheet once it
+    /// has loaded.
+    pub fn get(&mut self, path: &str) -> Option<AnimatedSheet> {
+        if let Some(sheet) = self.registered.sheets.get(path) {
+            return Some(sheet.clone());
+        }
+        let handle = self
+            .registered
+            .loading
+            .entry(path.to_string())
+            .or_insert_with(|| self.assets.load(path.to_string()));
+        let manifest = self.manifests.get(handle)?;
+        let texture = self.assets.load_with_settings(
+            manifest.texture.clone(),
+            |s: &mut ImageLoaderSettings| match &mut s.sampler {
+                ImageSampler::Default => s.sampler = ImageSampler::nearest(),
+                ImageSampler::Descriptor(sampler) => {
+                    *sampler = ImageSamplerDescriptor::nearest();
+                }
+            },
+        );
+        let layout = self.layouts.add(TextureAtlasLayout::from_grid(
+            UVec2::new(manifest.tile_size.0, manifest.tile_size.1),
+            manifest.columns as u32,
+            manifest.rows as u32,
+            None,
+            None,
+        ));
+        let grid = Spritesheet::new(manifest.columns, manifest.rows);
+        let library = &mut self.library;
+        let animations = manifest
+            .states
+            .iter()
+            .map(|(state, animations)| {
+                let ids = match animations {
+                    StateAnimations::Single(animation) => {
+                        StateIds::Single(animation.register(&grid, library))
+                    }
+                    StateAnimations::Directional(animations) => StateIds::Directional(
+                        animations
+                            .iter()
+                            .map(|(&direction, animation)| {
+                                (direction, animation.register(&grid, library))
+                            })
+                            .collect(),
+                    ),
+                };
+                (state.clone(), ids)
+            })
+            .collect();
+        let sheet = AnimatedSheet {
+            texture,
+            layout,
+            animations: SpriteAnimations(animations),
+        };
+        self.registered.loading.remove(path);
+        self.registered
+            .sheets
+            .insert(path.to_string(), sheet.clone());
+        Some(sheet)
+    }
+}
@@ -0,0 +1,177 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::Deserialize;
+
+use crate::{
+    animations::AnimatedSheets,
+    damage::DamageRoll,
+    dash::DashDefinition,
+    menu::{spawn_menu, MenuSelected},
+    meta::MetaProgress,
+    ron_loader::RonAssetApp,
+    stats::Stat,
+    GameState,
+};
+
+/// Playable characters, and the screen to pick one before a run.
+pub struct CharactersPlugin;
+impl Plugin for CharactersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_ron_asset::<CharacterDefinition>(&["character.ron"]);
+        app.add_systems(Startup, load_characters);
+        app.add_systems(OnEnter(GameState::CharacterSelect), setup_character_select);
+        app.add_systems(
+            Update,
+            character_selected.run_if(in_state(GameState::CharacterSelect)),
+        );
+        app.add_systems(
+            OnExit(GameState::CharacterSelect),
+            teardown_character_select,
+        );
+    }
+}
+
+/// Everything that sets one character apart, loaded from a `.character.ron` file.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct CharacterDefinition {
+    pub name: String,
+    pub description: String,
+    /// Asset path of the character's animation manifest.
+    pub animations: String,
+    /// Multiplies the colour of the sheet.
+    #[serde(default = "default_tint")]
+    pub tint: Color,
+    /// Base values of the character's stats, falling back to the defaults for any not set.
+    #[serde(default)]
+    pub stats: HashMap<Stat, f32>,
+    pub damage_roll: DamageRoll,
+    /// Asset path of the weapon the character starts every run with.
+    pub starting_weapon: String,
+    #[serde(default)]
+    pub dash: DashDefinition,
+    /// Meta progress unlock needed before the character can be picked; always playable if unset.
+    #[serde(default)]
+    pub unlock: Option<String>,
+}
+
+impl CharacterDefinition {
+    fn is_unlocked(&self, meta: &MetaProgress) -> bool {
+        self.unlock
+            .as_ref()
+            .map_or(true, |unlock| meta.is_unlocked(unlock))
+    }
+}
+
+fn default_tint() -> Color {
+    Color::WHITE
+}
+
+#[derive(Resource)]
+struct Characters(Vec<Handle<CharacterDefinition>>);
+
+impl Characters {
+    /// The characters that have loaded and can be picked, in menu order.
+    fn playable<'a>(
+        &'a self,
+        definitions: &'a Assets<CharacterDefinition>,
+        meta: &'a MetaProgress,
+    ) -> impl Iterator<Item = (&'a Handle<CharacterDefinition>, &'a CharacterDefinition)> {
+        self.0.iter().filter_map(move |handle| {
+            definitions
+                .get(handle)
+                .filter(|definition| definition.is_unlocked(meta))
+                .map(|definition| (handle, definition))
+        })
+    }
+}
+
+/// The character the next run is played as.
+#[derive(Resource)]
+pub struct SelectedCharacter(pub Handle<CharacterDefinition>);
+
+#[derive(Component)]
+struct CharacterSelectMenu;
+
+fn load_characters(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.insert_resource(Characters(vec![
+        assets.load("characters/acolyte.character.ron"),
+        assets.load("characters/warden.character.ron"),
+    ]));
+}
+
+fn setup_character_select(
+    mut commands: Commands,
+    characters: Res<Characters>,
+    definitions: Res<Assets<CharacterDefinition>>,
+    meta: Res<MetaProgress>,
+    mut sheets: AnimatedSheets,
+) {
+    let playable: Vec<_> = characters
+        .playable(&definitions, &meta)
+        .map(|(_, definition)| definition)
+        .collect();
+    // Start loading every character's sprites while one is chosen.
+    for definition in playable.iter() {
+        sheets.get(&definition.animations);
+    }
+    let mut lines: Vec<_> = playable
+        .iter()
+        .map(|definition| format!("{}: {}", definition.name, definition.description))
+        .collect();
+    lines.extend(
+        characters
+            .0
+            .iter()
+            .filter_map(|handle| definitions.get(handle))
+            .filter(|definition| !definition.is_unlocked(&meta))
+            .map(|definition| format!("{}: locked", definition.name)),
+    );
+    let mut options: Vec<_> = playable
+        .iter()
+        .map(|definition| definition.name.as_str())
+        .collect();
+    options.push("Back");
+    let menu = spawn_menu(&mut commands, "Choose a character", &lines, &options);
+    commands.entity(menu).insert(CharacterSelectMenu);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn character_selected(
+    mut commands: Commands,
+    mut selected: EventReader<MenuSelected>,
+    menus: Query<(), With<CharacterSelectMenu>>,
+    characters: Res<Characters>,
+    definitions: Res<Assets<CharacterDefinition>>,
+    meta: Res<MetaProgress>,
+    mut sheets: AnimatedSheets,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for selected in selected.read() {
+        if !menus.contains(selected.menu) {
+            continue;
+        }
+        // The menu only lists the characters that had loaded and are unlocked.
+        let character = characters
+            .playable(&definitions, &meta)
+            .nth(selected.option);
+        match character {
+            Some((character, definition)) => {
+                // Wait for the character's sprites, which are nearly always loaded by now.
+                if sheets.get(&definition.animations).is_none() {
+                    continue;
+                }
+                commands.insert_resource(SelectedCharacter(character.clone()));
+                next_state.set(GameState::Playing);
+            }
+            None => next_state.set(GameState::StartScreen),
+        }
+    }
+}
+
+fn teardown_character_select(
+    mut commands: Commands,
+    menus: Query<Entity, With<CharacterSelectMenu>>,
+) {
+    for menu in menus.iter() {
+        commands.entity(menu).despawn_recursive();
+    }
+}
@@ -0,0 +1,413 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashMap};
+use rand::{thread_rng, Rng};
+use serde::Deserialize;
+
+use crate::{Dead, GameState, Health, Hurt};
+
+/// Every source of damage sends a [`DamageEvent`], and [`resolve_damage`] is the only
+/// system that touches health.
+pub struct DamagePlugin;
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>();
+        app.add_event::<DamageDealt>();
+        app.add_event::<EntityKilled>();
+        app.add_systems(
+            Update,
+            tick_contact_damage.run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(
+            Update,
+            tick_invulnerability.run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(
+            PostUpdate,
+            resolve_damage.run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(
+            Update,
+            flash_hits.run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(Update, log_damage.run_if(in_state(GameState::Playing)));
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DamageType {
+    #[default]
+    Physical,
+    Fire,
+    Ice,
+    Lightning,
+    Holy,
+}
+
+impl DamageType {
+    /// Flashed on enemies hit by a type they are weak to.
+    pub fn colour(self) -> Color {
+        match self {
+            DamageType::Physical => Color::srgb(0.75, 0.75, 0.75),
+            DamageType::Fire => Color::srgb(1.0, 0.45, 0.1),
+            DamageType::Ice => Color::srgb(0.4, 0.85, 1.0),
+            DamageType::Lightning => Color::srgb(0.7, 0.5, 1.0),
+            DamageType::Holy => Color::srgb(1.0, 1.0, 0.75),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DamageFlags {
+    pub crit: bool,
+    /// Briefly stuns the target, as single hits do but lingering damage does not.
+    pub stagger: bool,
+    /// Set by the resolver when the target is weak to the damage type.
+    pub weakness: bool,
+}
+
+/// A request to damage `target`, before armour and resistances.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DamageEvent {
+    pub source: Entity,
+    pub target: Entity,
+    pub amount: u32,
+    pub damage_type: DamageType,
+    pub flags: DamageFlags,
+}
+
+/// Damage that made it through, after armour and resistances.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DamageDealt {
+    pub source: Entity,
+    pub target: Entity,
+    pub amount: u32,
+    pub damage_type: DamageType,
+    pub flags: DamageFlags,
+}
+
+/// Sent once, on the hit that takes an entity's health to zero.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct EntityKilled {
+    pub entity: Entity,
+    pub killer: Entity,
+}
+
+/// Flat reduction applied to every hit, which always does at least 1 damage.
+#[derive(Component, Default)]
+pub struct Armour(pub u32);
+
+/// Fraction of each damage type that is ignored; negative values take extra damage, and 1 or
+/// more makes the target immune.
+#[derive(Component, Default)]
+pub struct Resistances(pub HashMap<DamageType, f32>);
+
+/// Crit and variance stats. The player's are combined with the bonuses of the weapon
+/// dealing the damage, and rolled each time damage is applied.
+#[derive(Component, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(default)]
+pub struct DamageRoll {
+    /// Chance from 0 to 1 that a hit is critical.
+    pub crit_chance: f32,
+    /// Damage multiplier of a critical hit.
+    pub crit_multiplier: f32,
+    /// Damage varies by up to this fraction either way.
+    pub variance: f32,
+}
+impl DamageRoll {
+    pub fn combine(self, other: Self) -> Self {
+        Self {
+            crit_chance: self.crit_chance + other.crit_chance,
+            crit_multiplier: self.crit_multiplier + other.crit_multiplier,
+            variance: self.variance + other.variance,
+        }
+    }
+    /// Returns the damage dealt and whether it was a critical hit.
+    pub fn roll(&self, base: u32) -> (u32, bool) {
+        let mut rng = thread_rng();
+        let mut amount = base as f32;
+        if self.variance > 0.0 {
+            amount *= 1.0 + rng.gen_range(-self.variance..=self.variance);
+        }
+        let crit = rng.gen_bool(self.crit_chance.clamp(0.0, 1.0) as f64);
+        if crit {
+            amount *= self.crit_multiplier.max(1.0);
+        }
+        (amount.round().max(1.0) as u32, crit)
+    }
+    /// Rolls a hit from `source` on `target`.
+    pub fn event(
+        &self,
+        source: Entity,
+        target: Entity,
+        base: u32,
+        damage_type: DamageType,
+        stagger: bool,
+    ) -> DamageEvent {
+        let (amount, crit) = self.roll(base);
+        DamageEvent {
+            source,
+            target,
+            amount,
+            damage_type,
+            flags: DamageFlags {
+                crit,
+                stagger,
+                ..default()
+            },
+        }
+    }
+}
+
+/// Continuous damage being taken from everything currently in contact.
+#[derive(Component, Default, Debug)]
+pub struct DamageBuffer(pub Vec<Damage>);
+#[derive(Debug)]
+pub struct Damage {
+    source: Entity,
+    amount: u32,
+    damage_type: DamageType,
+    /// Time between applications while the source stays in contact.
+    tick: Timer,
+    roll: DamageRoll,
+}
+impl Damage {
+    pub fn new(
+        source: Entity,
+        amount: u32,
+        damage_type: DamageType,
+        interval: Duration,
+        roll: DamageRoll,
+    ) -> Self {
+        let mut tick = Timer::new(interval, TimerMode::Repeating);
+        // Start finished so contact hurts straight away.
+        tick.set_elapsed(interval);
+        Self {
+            source,
+            amount,
+            damage_type,
+            tick,
+            roll,
+        }
+    }
+}
+/// Lives as a child of whatever is dealing continuous damage to `target`, for as long as they touch.
+#[derive(Component)]
+pub struct DamageSource {
+    pub target: Entity,
+}
+
+fn tick_contact_damage(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut DamageBuffer)>,
+    mut damage_events: EventWriter<DamageEvent>,
+    time: Res<Time>,
+) {
+    for (target, mut buffer) in query.iter_mut() {
+        buffer.0.retain_mut(|damage| {
+            if commands.get_entity(damage.source).is_some() {
+                damage.tick.tick(time.delta());
+                if damage.tick.finished() {
+                    damage_events.send(damage.roll.event(
+                        damage.source,
+                        target,
+                        damage.amount,
+                        damage.damage_type,
+                        false,
+                    ));
+                }
+                true
+            } else {
+                false
+            }
+        });
+    }
+}
+
+fn tick_invulnerability(mut health: Query<&mut Health>, time: Res<Time>) {
+    for mut health in health.iter_mut() {
+        if let Some(invulnerability) = &mut health.invulnerability_timer {
+            invulnerability.tick(time.delta());
+        }
+    }
+}
+
+/// Damage left of a hit after resistance and armour, or `None` if the target is immune. Armour
+/// never takes a hit below 1 damage.
+fn mitigate(amount: u32, resistance: f32, armour: u32) -> Option<u32> {
+    if resistance >= 1.0 {
+        return None;
+    }
+    Some(
+        ((amount as f32 * (1.0 - resistance)).round() as u32)
+            .saturating_sub(armour)
+            .max(1),
+    )
+}
+
+/// Applies every damage event in the order it was sent.
+fn resolve_damage(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    mut targets: Query<(&mut Health, Option<&Armour>, Option<&Resistances>), Without<Dead>>,
+    mut damage_dealt: EventWriter<DamageDealt>,
+    mut entity_killed: EventWriter<EntityKilled>,
+) {
+    for event in damage_events.read() {
+        let Ok((mut health, armour, resistances)) = targets.get_mut(event.target) else {
+            continue;
+        };
+        if health.current == 0
+            || health
+                .invulnerability_timer
+                .as_ref()
+                .is_some_and(|invulnerability| !invulnerability.finished())
+        {
+            continue;
+        }
+        let resistance = resistances
+            .and_then(|resistances| resistances.0.get(&event.damage_type))
+            .copied()
+            .unwrap_or_default();
+        let Some(amount) = mitigate(
+            event.amount,
+            resistance,
+            armour.map_or(0, |armour| armour.0),
+        ) else {
+            continue;
+        };
+        health.current = health.current.saturating_sub(amount);
+        if health.invulnerability_duration > Duration::ZERO {
+            health.invulnerability_timer =
+                Some(Timer::new(health.invulnerability_duration, TimerMode::Once));
+        }
+        if event.flags.stagger {
+            commands.entity(event.target).try_insert(Hurt {
+                timer: Timer::from_seconds(0.5, TimerMode::Once),
+            });
+        }
+        damage_dealt.send(DamageDealt {
+            source: event.source,
+            target: event.target,
+            amount,
+            damage_type: event.damage_type,
+            flags: DamageFlags {
+                weakness: resistance < 0.0,
+                ..event.flags
+            },
+        });
+        if health.current == 0 {
+            entity_killed.send(EntityKilled {
+                entity: event.target,
+                killer: event.source,
+            });
+        }
+    }
+}
+
+/// Traces every hit and kill, which is far too many to log at a higher level.
+fn log_damage(mut damage_dealt: EventReader<DamageDealt>, mut killed: EventReader<EntityKilled>) {
+    for hit in damage_dealt.read() {
+        trace!(
+            "{:?} took {} {:?} damage from {:?}",
+            hit.target, hit.amount, hit.damage_type, hit.source
+        );
+    }
+    for killed in killed.read() {
+        trace!("{:?} was killed by {:?}", killed.entity, killed.killer);
+    }
+}
+
+/// Tints whatever took a notable hit until the flash runs out.
+#[derive(Component)]
+struct HitFlash {
+    timer: Timer,
+    /// The sprite's colour before the flash, restored afterwards.
+    original: Color,
+}
+const CRIT_TINT: Color = Color::srgb(1.0, 0.85, 0.2);
+
+/// Flashes the colour of the damage type on weaknesses, and gold on critical hits.
+fn flash_hits(
+    mut commands: Commands,
+    mut damage_dealt: EventReader<DamageDealt>,
+    mut flashing: Query<(Entity, &mut Sprite, Option<&mut HitFlash>)>,
+    time: Res<Time>,
+) {
+    for hit in damage_dealt.read() {
+        let colour = if hit.flags.weakness {
+            hit.damage_type.colour()
+        } else if hit.flags.crit {
+            CRIT_TINT
+        } else {
+            continue;
+        };
+        if let Ok((_, mut sprite, flash)) = flashing.get_mut(hit.target) {
+            let original = flash.map_or(sprite.color, |flash| flash.original);
+            sprite.color = colour;
+            commands.entity(hit.target).try_insert(HitFlash {
+                timer: Timer::from_seconds(0.15, TimerMode::Once),
+                original,
+            });
+        }
+    }
+    for (entity, mut sprite, flash) in flashing.iter_mut() {
+        let Some(mut flash) = flash else {
+            continue;
+        };
+        flash.timer.tick(time.delta());
+        if flash.timer.finished() {
+            sprite.color = flash.original;
+            commands.entity(entity).remove::<HitFlash>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn mitigate_keeps_at_least_one_damage() {
+        assert_eq!(mitigate(10, 0.0, 0), Some(10));
+        assert_eq!(mitigate(10, 0.5, 0), Some(5));
+        assert_eq!(mitigate(10, -0.5, 0), Some(15));
+        assert_eq!(mitigate(10, 0.0, 20), Some(1));
+        assert_eq!(mitigate(10, 0.99, 0), Some(1));
+    }
+
+    #[test]
+    fn full_resistance_deals_no_damage() {
+        assert_eq!(mitigate(10, 1.0, 0), None);
+        assert_eq!(mitigate(10, 1.5, 0), None);
+
+        let mut world = World::new();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<DamageDealt>>();
+        world.init_resource::<Events<EntityKilled>>();
+        let source = world.spawn_empty().id();
+        let target = world
+            .spawn((
+                Health {
+                    current: 10,
+                    max: 10,
+                    invulnerability_timer: None,
+                    invulnerability_duration: Duration::ZERO,
+                },
+                Resistances(HashMap::from_iter([(DamageType::Ice, 1.0)])),
+            ))
+            .id();
+        world.send_event(DamageEvent {
+            source,
+            target,
+            amount: 10,
+            damage_type: DamageType::Ice,
+            flags: DamageFlags::default(),
+        });
+        world.run_system_once(resolve_damage);
+        assert_eq!(world.get::<Health>(target).unwrap().current, 10);
+        assert!(world.resource::<Events<DamageDealt>>().is_empty());
+    }
+}
@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+
+use crate::{damage::DamageDealt, run::RunEntity, GameState, Player};
+
+pub struct DamageNumbersPlugin;
+impl Plugin for DamageNumbersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            spawn_damage_numbers.run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(
+            Update,
+            float_damage_numbers.run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Seconds a number takes to rise and fade out, counted from the first hit merged into it.
+const LIFETIME: f32 = 0.8;
+/// Hits on a target within this many seconds of the last hit on its number are added to it.
+const MERGE_WINDOW: f32 = 0.25;
+/// Pixels per second.
+const RISE_SPEED: f32 = 24.0;
+/// Drawn above everything in the world except chain and beam lines.
+const NUMBER_Z: f32 = 9.0;
+/// Text is rendered at twice the size and scaled down so it stays crisp under the zoomed camera.
+const TEXT_SCALE: f32 = 0.5;
+const FONT_SIZE: f32 = 24.0;
+const CRIT_FONT_SIZE: f32 = 32.0;
+
+const DAMAGE_COLOUR: Color = Color::WHITE;
+const CRIT_COLOUR: Color = Color::srgb(1.0, 0.85, 0.2);
+const PLAYER_DAMAGE_COLOUR: Color = Color::srgb(1.0, 0.2, 0.2);
+
+#[derive(Component)]
+struct DamageNumber {
+    target: Entity,
+    amount: u32,
+    crit: bool,
+    timer: Timer,
+    /// Seconds into `timer` that the last hit was merged in.
+    last_hit: f32,
+}
+
+fn spawn_damage_numbers(
+    mut commands: Commands,
+    mut damage_dealt: EventReader<DamageDealt>,
+    mut numbers: Query<(&mut DamageNumber, &mut Text)>,
+    targets: Query<(&GlobalTransform, Has<Player>)>,
+) {
+    for hit in damage_dealt.read() {
+        let Ok((target, is_player)) = targets.get(hit.target) else {
+            continue;
+        };
+        let colour = |crit: bool| {
+            if is_player {
+                PLAYER_DAMAGE_COLOUR
+            } else if crit {
+                CRIT_COLOUR
+            } else if hit.flags.weakness {
+                hit.damage_type.colour()
+            } else {
+                DAMAGE_COLOUR
+            }
+        };
+        if let Some((mut number, mut text)) = numbers.iter_mut().find(|(number, _)| {
+            number.target == hit.target
+                && number.timer.elapsed_secs() - number.last_hit < MERGE_WINDOW
+        }) {
+            number.amount += hit.amount;
+            number.crit |= hit.flags.crit;
+            number.last_hit = number.timer.elapsed_secs();
+            let section = &mut text.sections[0];
+            section.value = number.amount.to_string();
+            section.style.color = colour(number.crit);
+            if number.crit {
+                section.style.font_size = CRIT_FONT_SIZE;
+            }
+            continue;
+        }
+        let translation = target.translation().truncate() + Vec2::new(0.0, 16.0);
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    hit.amount.to_string(),
+                    TextStyle {
+                        font_size: if hit.flags.crit {
+                            CRIT_FONT_SIZE
+                        } else {
+                            FONT_SIZE
+                        },
+                        color: colour(hit.flags.crit),
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_translation(translation.extend(NUMBER_Z))
+                    .with_scale(Vec3::splat(TEXT_SCALE)),
+                ..default()
+            },
+            DamageNumber {
+                target: hit.target,
+                amount: hit.amount,
+                crit: hit.flags.crit,
+                timer: Timer::from_seconds(LIFETIME, TimerMode::Once),
+                last_hit: 0.0,
+            },
+            RunEntity,
+        ));
+    }
+}
+
+fn float_damage_numbers(
+    mut commands: Commands,
+    mut numbers: Query<(Entity, &mut DamageNumber, &mut Text, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut number, mut text, mut transform) in numbers.iter_mut() {
+        number.timer.tick(time.delta());
+        if number.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        transform.translation.y += RISE_SPEED * time.delta_seconds();
+        let alpha = number.timer.fraction_remaining();
+        text.sections[0].style.color.set_alpha(alpha);
+    }
+}
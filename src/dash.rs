@@ -0,0 +1,222 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_ineffable::prelude::*;
+use bevy_rapier2d::{
+    geometry::{Collider, CollisionGroups, ShapeCastOptions},
+    pipeline::QueryFilter,
+    plugin::RapierContext,
+};
+use serde::Deserialize;
+
+use crate::{
+    input::PlayerInput, pause::PlayState, GameState, Health, Player, ENEMY_GROUP, PLAYER_BODY,
+    PLAYER_GROUP, TERRAIN_GROUP,
+};
+
+/// A quick burst of movement the player can't be hurt during, on a cooldown.
+pub struct DashPlugin;
+impl Plugin for DashPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, start_dash.run_if(in_state(PlayState::Running)));
+        app.add_systems(
+            Update,
+            dash_movement
+                .after(start_dash)
+                .run_if(in_state(PlayState::Running)),
+        );
+        app.add_systems(Update, setup_dash_bar.run_if(in_state(GameState::Playing)));
+        app.add_systems(Update, update_dash_bar.run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// How a character dashes, set in their `.character.ron` file.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DashDefinition {
+    /// Pixels covered by a dash that isn't blocked.
+    pub distance: f32,
+    /// Seconds a dash takes.
+    pub duration: f32,
+    /// Seconds from the start of one dash until the next can start.
+    pub cooldown: f32,
+    /// Seconds the player can't be hurt for from the start of a dash.
+    pub invulnerability: f32,
+    /// Whether enemies stop a dash, or can be dashed through.
+    pub blocked_by_enemies: bool,
+}
+
+impl Default for DashDefinition {
+    fn default() -> Self {
+        Self {
+            distance: 96.0,
+            duration: 0.2,
+            cooldown: 3.0,
+            invulnerability: 0.3,
+            blocked_by_enemies: true,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Dash {
+    definition: DashDefinition,
+    cooldown: Timer,
+}
+
+impl Dash {
+    pub fn new(definition: DashDefinition) -> Self {
+        let mut cooldown = Timer::from_seconds(definition.cooldown, TimerMode::Once);
+        // Ready from the start of a run.
+        cooldown.tick(cooldown.duration());
+        Self {
+            definition,
+            cooldown,
+        }
+    }
+}
+
+/// Present on the player while they are dashing, in place of walking.
+#[derive(Component)]
+pub struct Dashing {
+    /// Pixels per second.
+    velocity: Vec2,
+    timer: Timer,
+}
+
+/// Stops a dash this far short of whatever blocks it.
+const DASH_SKIN: f32 = 0.5;
+/// Seconds the shortest dash takes, so a zero duration in a character file can't make the
+/// velocity infinite.
+const MIN_DASH_DURATION: f32 = 0.01;
+
+#[derive(Component)]
+struct DashBar(f32);
+
+fn start_dash(
+    mut commands: Commands,
+    bindings: Res<Ineffable>,
+    mut player: Query<(Entity, &Player, &mut Dash, &mut Health), Without<Dashing>>,
+) {
+    let Ok((entity, player, mut dash, mut health)) = player.get_single_mut() else {
+        return;
+    };
+    if !dash.cooldown.finished() || !bindings.just_pulsed(ineff!(PlayerInput::Dash)) {
+        return;
+    }
+    // Dash the way the player is moving, or the way they are aiming when standing still.
+    let direction = bindings
+        .direction_2d(ineff!(PlayerInput::Move))
+        .try_normalize()
+        .unwrap_or_else(|| player.aim());
+    let definition = &dash.definition;
+    let duration = definition.duration.max(MIN_DASH_DURATION);
+    commands.entity(entity).insert(Dashing {
+        velocity: direction * definition.distance / duration,
+        timer: Timer::from_seconds(duration, TimerMode::Once),
+    });
+    // Never cuts short the longer invulnerability that follows a hit.
+    let invulnerability = Duration::from_secs_f32(definition.invulnerability);
+    let remaining = health
+        .invulnerability_timer
+        .as_ref()
+        .map_or(Duration::ZERO, Timer::remaining);
+    if remaining < invulnerability {
+        health.invulnerability_timer = Some(Timer::new(invulnerability, TimerMode::Once));
+    }
+    dash.cooldown.reset();
+}
+
+/// Moves the player along their dash, ending it early against terrain and, if the player's
+/// dash is blocked by them, enemies.
+fn dash_movement(
+    mut commands: Commands,
+    mut player: Query<(Entity, &mut Transform, &mut Dash, Option<&mut Dashing>), With<Player>>,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>,
+) {
+    let Ok((entity, mut transform, mut dash, dashing)) = player.get_single_mut() else {
+        return;
+    };
+    dash.cooldown.tick(time.delta());
+    let Some(mut dashing) = dashing else {
+        return;
+    };
+    dashing.timer.tick(time.delta());
+    let step = dashing.velocity * time.delta_seconds();
+    let blockers = if dash.definition.blocked_by_enemies {
+        TERRAIN_GROUP | ENEMY_GROUP
+    } else {
+        TERRAIN_GROUP
+    };
+    let hit = rapier_context.cast_shape(
+        transform.translation.truncate(),
+        0.0,
+        step,
+        &Collider::cuboid(PLAYER_BODY.x, PLAYER_BODY.y),
+        ShapeCastOptions {
+            max_time_of_impact: 1.0,
+            // Dashing out of a crowd shouldn't be stopped by the enemies already touching.
+            stop_at_penetration: false,
+            ..default()
+        },
+        QueryFilter::new().groups(CollisionGroups::new(PLAYER_GROUP, blockers)),
+    );
+    let travelled = match hit {
+        Some((_, hit)) => {
+            let length = step.length();
+            step * ((hit.time_of_impact * length - DASH_SKIN).max(0.0) / length)
+        }
+        None => step,
+    };
+    transform.translation += travelled.extend(0.0);
+    if hit.is_some() || dashing.timer.finished() {
+        commands.entity(entity).remove::<Dashing>();
+    }
+}
+
+/// Adds a cooldown bar below the player's health and experience bars.
+fn setup_dash_bar(
+    mut commands: Commands,
+    players: Query<Entity, Added<Dash>>,
+    assets: Res<AssetServer>,
+) {
+    for player in players.iter() {
+        commands.entity(player).with_children(|player| {
+            player.spawn(SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(32.0, 4.0)),
+                    ..default()
+                },
+                texture: assets.load("bars/background.png"),
+                transform: Transform::from_xyz(0., -43.0, 3.),
+                ..default()
+            });
+            player
+                .spawn(SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::new(32.0, 4.0)),
+                        color: Color::linear_rgba(0.9, 0.8, 0.2, 1.0),
+                        ..default()
+                    },
+                    texture: assets.load("bars/bar.png"),
+                    transform: Transform::from_xyz(0., -43.0, 3.1),
+                    ..default()
+                })
+                .insert(DashBar(32.0));
+        });
+    }
+}
+
+/// Fills up as the dash cooldown runs down.
+fn update_dash_bar(mut bars: Query<(&DashBar, &mut Sprite, &Parent)>, dashes: Query<&Dash>) {
+    for (bar, mut sprite, parent) in bars.iter_mut() {
+        let Ok(dash) = dashes.get(parent.get()) else {
+            continue;
+        };
+        sprite.custom_size = Some(Vec2::new(
+            bar.0 * dash.cooldown.fraction(),
+            sprite.custom_size.unwrap().y,
+        ));
+    }
+}
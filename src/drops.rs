@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use bevy_spritesheet_animation::library::SpritesheetLibrary;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::{
+    pickups::{spawn_experience_pickup, spawn_pickup, ExperienceGem, PickupKind},
+    run::RunSeed,
+    stats::{Stat, Stats},
+    Dead, GameState, Player,
+};
+
+/// What enemies leave behind when they die.
+pub struct DropsPlugin;
+impl Plugin for DropsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Playing), seed_drops);
+        app.add_systems(Update, drop_loot.run_if(in_state(GameState::Playing)));
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum Loot {
+    Experience(ExperienceGem),
+    Pickup(PickupKind),
+}
+
+/// The loot of one kind of enemy, set in its `.enemy.ron` file.
+#[derive(Component, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct DropTable {
+    /// Dropped every time.
+    pub guaranteed: Vec<Loot>,
+    /// One of these is dropped, picked by weight.
+    pub weighted: Vec<(Loot, u32)>,
+    /// Each rolled for separately, with the chance from 0 to 1 multiplied by the player's luck.
+    pub chances: Vec<(Loot, f64)>,
+}
+
+impl DropTable {
+    fn roll(&self, rng: &mut impl Rng, luck: f32) -> Vec<Loot> {
+        let mut loot = self.guaranteed.clone();
+        if let Ok((weighted, _)) = self.weighted.choose_weighted(rng, |(_, weight)| *weight) {
+            loot.push(*weighted);
+        }
+        for (chance, probability) in self.chances.iter() {
+            if rng.gen_bool((probability * luck as f64).clamp(0.0, 1.0)) {
+                loot.push(*chance);
+            }
+        }
+        loot
+    }
+}
+
+/// Rolls every drop, seeded by the run. A retried run only drops the same loot if enemies are
+/// killed in the same order, which depends on how it's played.
+#[derive(Resource)]
+struct DropRng(StdRng);
+
+fn seed_drops(mut commands: Commands, seed: Res<RunSeed>) {
+    commands.insert_resource(DropRng(StdRng::seed_from_u64(seed.0 as u64)));
+}
+
+/// How far apart the drops of one enemy are spread.
+const DROP_SPACING: f32 = 16.0;
+
+fn drop_loot(
+    mut commands: Commands,
+    dead: Query<(&Transform, &DropTable), Added<Dead>>,
+    player: Query<&Stats, With<Player>>,
+    mut rng: ResMut<DropRng>,
+    library: Res<SpritesheetLibrary>,
+    mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    assets: Res<AssetServer>,
+) {
+    let luck = player
+        .get_single()
+        .map_or(1.0, |stats| stats.get(Stat::Luck));
+    for (transform, table) in dead.iter() {
+        let mut origin = *transform;
+        origin.translation.z = 1.0;
+        // Enemies can be scaled up, which their drops shouldn't be.
+        origin.scale = Vec3::ONE;
+        for loot in table.roll(&mut rng.0, luck) {
+            match loot {
+                Loot::Experience(gem) => commands.append(&mut spawn_experience_pickup(
+                    &library,
+                    &mut atlas_layouts,
+                    &assets,
+                    gem,
+                    origin,
+                )),
+                Loot::Pickup(kind) => spawn_pickup(&mut commands, kind, origin),
+            }
+            origin.translation.x += DROP_SPACING;
+        }
+    }
+}
@@ -1,29 +1,34 @@
 use std::time::Duration;
 
-use bevy::{
-    prelude::*,
-    render::texture::{ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor},
-};
+use bevy::{prelude::*, utils::HashMap};
 use bevy_rapier2d::{
     control::KinematicCharacterController,
     dynamics::{LockedAxes, RigidBody},
     geometry::{Collider, CollisionGroups, Restitution},
     pipeline::CollisionEvent,
 };
-use bevy_spritesheet_animation::{
-    animation::AnimationDuration, component::SpritesheetAnimation, library::SpritesheetLibrary,
-    spritesheet::Spritesheet,
-};
-use rand::{thread_rng, Rng};
+use bevy_spritesheet_animation::component::SpritesheetAnimation;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::Deserialize;
 
 use crate::{
-    pickups::spawn_experience_pickup, DamageBuffer, DamageSource, Dead, GameState, Health, Hurt, Level, Player, ENEMY_GROUP, PLAYER_GROUP, PROJECTILE_GROUP
+    animations::{self, AnimatedSheet, AnimatedSheets, Direction, SpriteAnimations},
+    damage::{
+        Damage, DamageBuffer, DamageRoll, DamageSource, DamageType, EntityKilled, Resistances,
+    },
+    drops::DropTable,
+    ron_loader::RonAssetApp,
+    run::{RunEntity, RunSeed},
+    Dead, GameState, Health, Hurt, Level, Player, RuntimeEnded, ENEMY_GROUP, PLAYER_GROUP,
+    PROJECTILE_GROUP,
 };
 pub struct EnemiesPlugin;
 impl Plugin for EnemiesPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_slime_animations);
+        app.init_ron_asset::<EnemyDefinition>(&["enemy.ron"]);
+        app.add_systems(Startup, load_enemy_definitions);
         app.add_systems(Update, spawn_slime.run_if(in_state(GameState::Playing)));
+        app.add_systems(Update, spawn_reaper.run_if(in_state(GameState::Playing)));
         app.add_systems(Update, move_slime.run_if(in_state(GameState::Playing)));
         app.add_systems(
             Update,
@@ -47,205 +52,317 @@ impl Plugin for EnemiesPlugin {
                 .run_if(in_state(GameState::Playing))
                 .after(move_slime),
         );
-        app.add_systems(Update, slime_drop.run_if(in_state(GameState::Playing)));
         app.insert_resource(SlimeSpawn {
-            cooldown: Timer::from_seconds(3.0, TimerMode::Once),
-            cooldown_func: |time| {
-                let delay = (time.as_secs_f32() / 150.0).cos() * 2.5;
-                Duration::from_secs_f32(delay.max(0.0))
+            pending: 0.0,
+            difficulty_func: |time| {
+                // Grows quadratically, so endless runs keep getting harder.
+                let minutes = time.as_secs_f32() / 60.0;
+                1.0 + 0.1 * minutes + 0.01 * minutes * minutes
             },
         });
+        app.init_resource::<ReaperPending>();
+        app.add_systems(OnEnter(GameState::Playing), reset_slime_spawn);
+        app.add_systems(OnEnter(GameState::Playing), seed_spawns);
+        app.add_systems(OnEnter(GameState::Playing), reset_reaper_pending);
+        app.add_systems(OnEnter(GameState::Playing), load_enemy_animations);
     }
 }
 
-fn setup_slime_animations(mut library: ResMut<SpritesheetLibrary>) {
-    let sheet = Spritesheet::new(6, 6);
-    let clip = library.new_clip(|clip| {
-        clip.push_frame_indices(sheet.row_partial(0, 0..6));
-    });
-    let animation = library.new_animation(|animation| {
-        animation.add_stage(clip.into());
-    });
-    let walk_left_clip = library.new_clip(|clip| {
-        clip.push_frame_indices(sheet.row_partial(2, 0..6));
-    });
-    let walk_left_animation = library.new_animation(|animation| {
-        animation.add_stage(walk_left_clip.into());
-    });
-    let walk_right_clip = library.new_clip(|clip| {
-        clip.push_frame_indices(sheet.row_partial(1, 0..6));
-    });
-    let walk_right_animation = library.new_animation(|animation| {
-        animation.add_stage(walk_right_clip.into());
-    });
-    let hurt_clip = library.new_clip(|clip| {
-        clip.push_frame_indices(sheet.row_partial(4, 0..4));
-        clip.set_default_duration(AnimationDuration::PerCycle(500));
-    });
-    let death_clip = library.new_clip(|clip| {
-        clip.push_frame_indices(sheet.row_partial(5, 0..4));
-        clip.set_default_duration(AnimationDuration::PerCycle(1000));
-    });
-    let death_animation = library.new_animation(|animation| {
-        animation.add_stage(hurt_clip.into());
-        animation.add_stage(death_clip.into());
-    });
+/// Stats of one kind of slime, loaded from a `.enemy.ron` file.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct EnemyDefinition {
+    pub name: String,
+    pub health: u32,
+    /// Contact damage dealt to the player.
+    pub damage: u32,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    /// Pixels per second.
+    pub speed: f32,
+    pub tint: Color,
+    /// Asset path of the enemy's animation manifest.
+    pub animations: String,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub resistances: HashMap<DamageType, f32>,
+    #[serde(default)]
+    pub drops: DropTable,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
 
-    let hurt_animation = library.new_animation(|animation| {
-        animation.add_stage(hurt_clip.into());
+#[derive(Resource)]
+struct EnemyDefinitions {
+    /// Every kind of enemy that spawns over the course of a run.
+    spawns: Vec<Handle<EnemyDefinition>>,
+    reaper: Handle<EnemyDefinition>,
+}
+
+fn load_enemy_definitions(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.insert_resource(EnemyDefinitions {
+        spawns: vec![
+            assets.load("enemies/slime.enemy.ron"),
+            assets.load("enemies/ember_slime.enemy.ron"),
+            assets.load("enemies/frost_slime.enemy.ron"),
+        ],
+        reaper: assets.load("enemies/reaper.enemy.ron"),
     });
-    library
-        .name_animation(animation, SLIME_IDLE_ANIMATION)
-        .unwrap();
-    library
-        .name_animation(walk_left_animation, SLIME_WALK_LEFT_ANIMATION)
-        .unwrap();
-    library
-        .name_animation(walk_right_animation, SLIME_WALK_RIGHT_ANIMATION)
-        .unwrap();
-    library
-        .name_animation(death_animation, SLIME_DEATH_ANIMATION)
-        .unwrap();
-    library
-        .name_animation(hurt_animation, SLIME_HURT_ANIMATION)
-        .unwrap();
 }
-const SLIME_IDLE_ANIMATION: &str = "slime idle";
-const SLIME_WALK_LEFT_ANIMATION: &str = "slime walk left";
-const SLIME_WALK_RIGHT_ANIMATION: &str = "slime walk right";
-const SLIME_DEATH_ANIMATION: &str = "slime death";
-const SLIME_HURT_ANIMATION: &str = "slime hurt";
+
+/// Enemies spawned per second at the start of a run, scaled up by difficulty.
+const BASE_SPAWN_RATE: f32 = 0.4;
+/// Caps how many enemies can spawn in one frame, so late endless runs don't hitch.
+const MAX_SPAWNS_PER_FRAME: f32 = 8.0;
+
 #[derive(Resource)]
 struct SlimeSpawn {
-    cooldown: Timer,
-    cooldown_func: fn(Duration) -> Duration,
+    /// Enemies owed by the spawn rate that haven't spawned yet.
+    pending: f32,
+    /// Multiplies enemy health and spawn rate, and the square root of it enemy damage.
+    difficulty_func: fn(Duration) -> f32,
+}
+
+/// Starts loading the sprites of every enemy before any of them spawn.
+fn load_enemy_animations(
+    enemy_definitions: Res<EnemyDefinitions>,
+    definitions: Res<Assets<EnemyDefinition>>,
+    mut sheets: AnimatedSheets,
+) {
+    let handles = enemy_definitions
+        .spawns
+        .iter()
+        .chain([&enemy_definitions.reaper]);
+    for definition in handles.filter_map(|handle| definitions.get(handle)) {
+        sheets.get(&definition.animations);
+    }
+}
+
+fn reset_slime_spawn(mut slime_spawn: ResMut<SlimeSpawn>) {
+    slime_spawn.pending = 0.0;
+}
+
+/// Picks which kind of enemy spawns and how far from the player, seeded by the run. A retried
+/// run rolls the same sequence of kinds and offsets, but where enemies land still depends on
+/// where the player has moved.
+#[derive(Resource)]
+struct SpawnRng(StdRng);
+
+fn seed_spawns(mut commands: Commands, seed: Res<RunSeed>) {
+    // Kept apart from the drop seed, so spawns and drops don't roll in step.
+    commands.insert_resource(SpawnRng(StdRng::seed_from_u64((seed.0 as u64) << 32)));
 }
 #[allow(clippy::too_many_arguments)]
 fn spawn_slime(
     mut commands: Commands,
-    library: ResMut<SpritesheetLibrary>,
-    mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
-    assets: Res<AssetServer>,
+    mut sheets: AnimatedSheets,
     player: Query<&Transform, With<Player>>,
     time: Res<Time>,
     mut slime_spawn: ResMut<SlimeSpawn>,
+    mut rng: ResMut<SpawnRng>,
     level: Res<Level>,
+    enemy_definitions: Res<EnemyDefinitions>,
+    definitions: Res<Assets<EnemyDefinition>>,
 ) {
-    // Space was pressed
-    slime_spawn.cooldown.tick(time.delta());
-    if !slime_spawn.cooldown.just_finished() {
+    // The reaper is the only thing left to fight once time is up.
+    if level.runtime.finished() {
         return;
     }
-    slime_spawn.cooldown = Timer::new(
-        (slime_spawn.cooldown_func)(level.runtime.elapsed()),
-        TimerMode::Once,
-    );
+    let difficulty = (slime_spawn.difficulty_func)(level.runtime.elapsed());
+    slime_spawn.pending = (slime_spawn.pending
+        + BASE_SPAWN_RATE * difficulty * time.delta_seconds())
+    .min(MAX_SPAWNS_PER_FRAME);
 
     let player_translation = player.single().translation;
-    let mut origin = player_translation;
-    let offset_x: f32 = thread_rng().gen_range(-256.0..256.0);
-    let offset_y: f32 = thread_rng().gen_range(-256.0..256.0);
-    origin.x += offset_x;
-    origin.y += offset_y;
-    if player_translation.distance(origin) < 32.0 {
-        origin += (origin - player_translation).normalize() * 32.0
+    while slime_spawn.pending >= 1.0 {
+        slime_spawn.pending -= 1.0;
+        let mut origin = player_translation;
+        let offset_x: f32 = rng.0.gen_range(-256.0..256.0);
+        let offset_y: f32 = rng.0.gen_range(-256.0..256.0);
+        origin.x += offset_x;
+        origin.y += offset_y;
+        if player_translation.distance(origin) < 32.0 {
+            origin += (origin - player_translation).normalize() * 32.0
+        }
+        let Some(definition) = enemy_definitions
+            .spawns
+            .choose(&mut rng.0)
+            .and_then(|handle| definitions.get(handle))
+        else {
+            return;
+        };
+        let Some(sheet) = sheets.get(&definition.animations) else {
+            continue;
+        };
+        spawn_enemy(&mut commands, sheet, definition, difficulty, origin);
     }
-    let slime = Slime { damage: 1 };
-    let texture =
-        assets.load_with_settings(
-            "enemies/Slime.png",
-            |s: &mut ImageLoaderSettings| match &mut s.sampler {
-                ImageSampler::Default => s.sampler = ImageSampler::nearest(),
-                ImageSampler::Descriptor(sampler) => {
-                    *sampler = ImageSamplerDescriptor::nearest();
-                }
-            },
-        );
-    let layout = atlas_layouts.add(TextureAtlasLayout::from_grid(
-        UVec2::new(100, 100),
-        6,
-        6,
-        None,
-        None,
-    ));
+}
+
+/// Spawns a slime with the stats and look of `definition`, made tougher by `difficulty`.
+fn spawn_enemy(
+    commands: &mut Commands,
+    sheet: AnimatedSheet,
+    definition: &EnemyDefinition,
+    difficulty: f32,
+    mut origin: Vec3,
+) -> Entity {
+    let health = (definition.health as f32 * difficulty).round() as u32;
+    let idle_animation = sheet.animations.get(animations::IDLE, Direction::Down);
+    let slime = Slime {
+        damage: (definition.damage as f32 * difficulty.sqrt()).round() as u32,
+        damage_type: definition.damage_type,
+        speed: definition.speed,
+        animations: sheet.animations,
+    };
     origin.z = 5.0;
-    commands
+    let enemy = commands
         .spawn(slime)
+        .insert(Enemy)
+        .insert(RunEntity)
         .insert(SpriteBundle {
-            texture,
-            transform: Transform::from_translation(origin),
+            sprite: Sprite {
+                color: definition.tint,
+                ..default()
+            },
+            texture: sheet.texture,
+            transform: Transform::from_translation(origin)
+                .with_scale(Vec3::splat(definition.scale)),
             ..default()
         })
         .insert(TextureAtlas {
-            layout,
+            layout: sheet.layout,
             ..default()
         })
         .insert(Collider::cuboid(16.0, 16.0))
         .insert(LockedAxes::ROTATION_LOCKED)
         .insert(Restitution::coefficient(0.5))
         .insert(RigidBody::Dynamic)
+        .insert(Name::new(definition.name.clone()))
         .insert(Health {
-            current: 2,
-            max: 2,
+            current: health,
+            max: health,
             invulnerability_timer: None,
             invulnerability_duration: Duration::ZERO,
         })
         .insert(DamageBuffer::default())
+        .insert(Resistances(definition.resistances.clone()))
+        .insert(definition.drops.clone())
         .insert(CollisionGroups::new(
             ENEMY_GROUP,
             ENEMY_GROUP|PLAYER_GROUP | PROJECTILE_GROUP,
         ))
         .insert(KinematicCharacterController::default())
-        .insert(SpritesheetAnimation::from_id(
-            library.animation_with_name(SLIME_IDLE_ANIMATION).unwrap(),
-        ));
+        .id();
+    if let Some(animation) = idle_animation {
+        commands
+            .entity(enemy)
+            .insert(SpritesheetAnimation::from_id(animation));
+    }
+    enemy
+}
+
+/// The final encounter, spawned when the level's runtime is up. Defeating it wins the run.
+#[derive(Component)]
+pub struct Reaper;
+
+/// How far from the player the reaper appears.
+const REAPER_DISTANCE: f32 = 320.0;
+
+/// Whether time is up but the reaper hasn't spawned yet.
+#[derive(Resource, Default)]
+struct ReaperPending(bool);
+
+fn reset_reaper_pending(mut pending: ResMut<ReaperPending>) {
+    pending.0 = false;
 }
+
+/// Spawns the reaper once time is up, trying again every frame until its definition and
+/// sprites have loaded.
+#[allow(clippy::too_many_arguments)]
+fn spawn_reaper(
+    mut commands: Commands,
+    mut time_up: EventReader<RuntimeEnded>,
+    mut pending: ResMut<ReaperPending>,
+    mut rng: ResMut<SpawnRng>,
+    mut sheets: AnimatedSheets,
+    player: Query<&Transform, With<Player>>,
+    enemy_definitions: Res<EnemyDefinitions>,
+    definitions: Res<Assets<EnemyDefinition>>,
+) {
+    if time_up.read().count() > 0 {
+        pending.0 = true;
+    }
+    if !pending.0 {
+        return;
+    }
+    let (Ok(player), Some(definition)) = (
+        player.get_single(),
+        definitions.get(&enemy_definitions.reaper),
+    ) else {
+        return;
+    };
+    let Some(sheet) = sheets.get(&definition.animations) else {
+        return;
+    };
+    pending.0 = false;
+    let direction = Vec2::from_angle(rng.0.gen_range(0.0..std::f32::consts::TAU));
+    let reaper = spawn_enemy(
+        &mut commands,
+        sheet,
+        definition,
+        // The reaper's definition is already tuned for the end of a run.
+        1.0,
+        player.translation + (direction * REAPER_DISTANCE).extend(0.0),
+    );
+    commands.entity(reaper).insert(Reaper);
+}
+
 fn move_slime(
     mut commands: Commands,
     mut slimes: Query<
-        (Entity, &Transform, &mut KinematicCharacterController),
-        (With<Slime>, Without<Player>, Without<Dead>, Without<Hurt>),
+        (Entity, &Slime, &Transform, &mut KinematicCharacterController),
+        (Without<Player>, Without<Dead>, Without<Hurt>),
     >,
     player: Query<&Transform, With<Player>>,
     time: Res<Time>,
-    library: Res<SpritesheetLibrary>,
 ) {
     let Ok(player) = player.get_single() else {
         return;
     };
-    for (slime_entity, slime_transform, mut slime_controller) in slimes.iter_mut() {
+    for (slime_entity, slime, slime_transform, mut slime_controller) in slimes.iter_mut() {
         let direction = (player.translation - slime_transform.translation).normalize();
-        slime_controller.translation = Some((direction * 32.0 * time.delta_seconds()).truncate());
+        slime_controller.translation =
+            Some((direction * slime.speed * time.delta_seconds()).truncate());
         let moving = direction.length() > 0.0;
-        let animation = if moving {
-            if direction.x > 0.0 {
-                SLIME_WALK_RIGHT_ANIMATION
-            } else {
-                SLIME_WALK_LEFT_ANIMATION
-            }
+        let state = if moving { animations::WALK } else { animations::IDLE };
+        let facing = if direction.x > 0.0 {
+            Direction::Right
         } else {
-            SLIME_IDLE_ANIMATION
+            Direction::Left
         };
         let Some(mut slime_entity) = commands.get_entity(slime_entity) else {
             return;
         };
-        slime_entity.try_insert(SpritesheetAnimation::from_id(
-            library.animation_with_name(animation).unwrap(),
-        ));
+        if let Some(animation) = slime.animations.get(state, facing) {
+            slime_entity.try_insert(SpritesheetAnimation::from_id(animation));
+        }
     }
 }
 
 fn slime_death(
     mut commands: Commands,
-    slimes: Query<(Entity, &Health), (With<Slime>, Without<Dead>)>,
-    library: Res<SpritesheetLibrary>,
+    mut killed: EventReader<EntityKilled>,
+    slimes: Query<(Entity, &Slime), Without<Dead>>,
 ) {
-    for (slime, health) in slimes.iter() {
-        if health.current == 0 {
-            let mut slime = commands.entity(slime);
-            slime.try_insert(SpritesheetAnimation::from_id(
-                library.animation_with_name(SLIME_DEATH_ANIMATION).unwrap(),
-            ));
+    for killed in killed.read() {
+        if let Ok((entity, slime)) = slimes.get(killed.entity) {
+            let death_animation = slime.animations.get(animations::DEATH, Direction::Down);
+            let mut slime = commands.entity(entity);
+            if let Some(animation) = death_animation {
+                slime.try_insert(SpritesheetAnimation::from_id(animation));
+            }
             slime.try_insert(Dead {
                 timer: Timer::from_seconds(1.0, TimerMode::Once),
             });
@@ -257,13 +374,14 @@ fn slime_death(
 }
 fn slime_hurt(
     mut commands: Commands,
-    slimes: Query<Entity, (With<Slime>, Without<Dead>, Added<Hurt>)>,
-    library: Res<SpritesheetLibrary>,
+    slimes: Query<(Entity, &Slime), (Without<Dead>, Added<Hurt>)>,
 ) {
-    for slime in slimes.iter() {
-        commands.entity(slime).insert(SpritesheetAnimation::from_id(
-            library.animation_with_name(SLIME_HURT_ANIMATION).unwrap(),
-        ));
+    for (entity, slime) in slimes.iter() {
+        if let Some(animation) = slime.animations.get(animations::HURT, Direction::Down) {
+            commands
+                .entity(entity)
+                .insert(SpritesheetAnimation::from_id(animation));
+        }
     }
 }
 
@@ -280,35 +398,23 @@ fn hurt_timer(
     }
 }
 
-fn slime_drop(
-    mut commands: Commands,
-    dead_slimes: Query<&Transform, (With<Slime>, Added<Dead>)>,
-    library: Res<SpritesheetLibrary>,
-    mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
-    assets: Res<AssetServer>,
-) {
-    for transform in dead_slimes.iter() {
-        let mut origin = *transform;
-        origin.translation.z = 1.0;
-        commands.append(&mut spawn_experience_pickup(
-            &library,
-            &mut atlas_layouts,
-            &assets,
-            origin,
-        ))
-    }
-}
+/// Marks anything the player's weapons should seek out and damage.
+#[derive(Component)]
+pub struct Enemy;
 
 #[derive(Component, Default)]
 struct Slime {
     damage: u32,
+    damage_type: DamageType,
+    speed: f32,
+    animations: SpriteAnimations,
 }
 
 fn slime_hurt_player(
     mut commands: Commands,
     mut collision_events: EventReader<CollisionEvent>,
     slime: Query<(&Slime, Option<&Children>)>,
-    damage_source: Query<Entity, With<DamageSource>>,
+    damage_source: Query<&DamageSource>,
     mut player: Query<&mut DamageBuffer, With<Player>>,
 ) {
     for collision_event in collision_events.read() {
@@ -317,22 +423,28 @@ fn slime_hurt_player(
                 if let Ok((slime, _)) = slime.get(*a) {
                     if let Ok(mut player) = player.get_mut(*b) {
                         // info!("Slime Started Colliding With Player");
-                        let damage_entity = commands.spawn(DamageSource).id();
+                        let damage_entity = commands.spawn(DamageSource { target: *b }).id();
                         commands.entity(*a).add_child(damage_entity);
-                        player.0.push(crate::Damage {
-                            source: damage_entity,
-                            amount: slime.damage,
-                        });
+                        player.0.push(Damage::new(
+                            damage_entity,
+                            slime.damage,
+                            slime.damage_type,
+                            Duration::ZERO,
+                            DamageRoll::default(),
+                        ));
                     }
                 } else if let Ok((slime, _)) = slime.get(*b) {
                     if let Ok(mut player) = player.get_mut(*a) {
                         // info!("Slime Started Colliding With Player");
-                        let damage_entity = commands.spawn(DamageSource).id();
+                        let damage_entity = commands.spawn(DamageSource { target: *a }).id();
                         commands.entity(*b).add_child(damage_entity);
-                        player.0.push(crate::Damage {
-                            source: damage_entity,
-                            amount: slime.damage,
-                        });
+                        player.0.push(Damage::new(
+                            damage_entity,
+                            slime.damage,
+                            slime.damage_type,
+                            Duration::ZERO,
+                            DamageRoll::default(),
+                        ));
                     }
                 }
             }
@@ -343,8 +455,8 @@ fn slime_hurt_player(
                         if let Some(children) = children {
                             // info!("Slime Had Children");
                             for &child in children.iter() {
-                                if let Ok(source) = damage_source.get(child) {
-                                    commands.entity(source).despawn_recursive();
+                                if damage_source.contains(child) {
+                                    commands.entity(child).despawn_recursive();
                                 }
                             }
                         }
@@ -355,8 +467,8 @@ fn slime_hurt_player(
                         if let Some(children) = children {
                             // info!("Slime Had Children");
                             for &child in children.iter() {
-                                if let Ok(source) = damage_source.get(child) {
-                                    commands.entity(source).despawn_recursive();
+                                if damage_source.contains(child) {
+                                    commands.entity(child).despawn_recursive();
                                 }
                             }
                         }
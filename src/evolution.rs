@@ -0,0 +1,126 @@
+use std::path::Path;
+
+use bevy::{prelude::*, utils::HashMap};
+use rand::{seq::IteratorRandom, thread_rng};
+use serde::Deserialize;
+
+use crate::{
+    passives::{PassiveDefinition, PassiveItems},
+    pickups::ChestOpened,
+    ron_loader::RonAssetApp,
+    weapons::{Weapon, WeaponDefinition, Weapons},
+    GameState,
+};
+
+pub struct EvolutionPlugin;
+impl Plugin for EvolutionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_ron_asset::<EvolutionRecipes>(&["evolutions.ron"]);
+        app.init_resource::<DiscoveredEvolutions>();
+        app.add_systems(Startup, load_recipes);
+        app.add_systems(OnEnter(GameState::Playing), reset_discovered);
+        app.add_systems(Update, open_chests.run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// Every weapon evolution, loaded from a `.evolutions.ron` file.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct EvolutionRecipes(pub Vec<EvolutionRecipe>);
+
+/// A max level `weapon` held alongside `passive` becomes `evolved` at the next chest.
+#[derive(Deserialize, Debug)]
+pub struct EvolutionRecipe {
+    /// Asset path of the weapon that evolves.
+    pub weapon: String,
+    /// Asset path of the passive item required.
+    pub passive: String,
+    /// Asset path of the weapon it evolves into.
+    pub evolved: String,
+}
+
+/// Asset paths of the weapons evolved so far this run, keyed by the path of the weapon each
+/// evolved from.
+#[derive(Resource, Default)]
+pub struct DiscoveredEvolutions(pub HashMap<String, String>);
+
+#[derive(Resource)]
+struct Recipes(Handle<EvolutionRecipes>);
+
+fn load_recipes(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.insert_resource(Recipes(assets.load("weapons/base.evolutions.ron")));
+}
+
+fn reset_discovered(mut discovered: ResMut<DiscoveredEvolutions>) {
+    discovered.0.clear();
+}
+
+/// Evolves the first weapon that is ready to, otherwise levels up a random weapon or
+/// passive item.
+#[allow(clippy::too_many_arguments)]
+fn open_chests(
+    mut commands: Commands,
+    mut chests: EventReader<ChestOpened>,
+    mut players: Query<(&mut Weapons, &mut PassiveItems)>,
+    recipes: Res<Recipes>,
+    all_recipes: Res<Assets<EvolutionRecipes>>,
+    definitions: Res<Assets<WeaponDefinition>>,
+    passive_definitions: Res<Assets<PassiveDefinition>>,
+    assets: Res<AssetServer>,
+    mut discovered: ResMut<DiscoveredEvolutions>,
+) {
+    for chest in chests.read() {
+        let Ok((mut weapons, mut passives)) = players.get_mut(chest.player) else {
+            continue;
+        };
+        let recipe = all_recipes.get(&recipes.0).and_then(|recipes| {
+            weapons.0.iter().enumerate().find_map(|(index, weapon)| {
+                let definition = definitions.get(&weapon.definition)?;
+                if weapon.level < definition.max_level {
+                    return None;
+                }
+                let path = weapon.definition.path()?;
+                recipes
+                    .0
+                    .iter()
+                    .find(|recipe| {
+                        path.path() == Path::new(&recipe.weapon)
+                            && passives.contains_path(&recipe.passive)
+                    })
+                    .map(|recipe| (index, recipe))
+            })
+        });
+        if let Some((index, recipe)) = recipe {
+            let weapon = &mut weapons.0[index];
+            for effect in weapon.effects.drain(..) {
+                commands.entity(effect).despawn_recursive();
+            }
+            *weapon = Weapon::new(assets.load(recipe.evolved.clone()));
+            discovered
+                .0
+                .insert(recipe.weapon.clone(), recipe.evolved.clone());
+            info!("Evolved {} into {}", recipe.weapon, recipe.evolved);
+            continue;
+        }
+        let weapon = weapons
+            .0
+            .iter_mut()
+            .filter(|weapon| {
+                definitions
+                    .get(&weapon.definition)
+                    .is_some_and(|definition| weapon.level < definition.max_level)
+            })
+            .map(|weapon| &mut weapon.level);
+        let passive = passives
+            .0
+            .iter_mut()
+            .filter(|item| {
+                passive_definitions
+                    .get(&item.definition)
+                    .is_some_and(|definition| item.level < definition.max_level)
+            })
+            .map(|item| &mut item.level);
+        if let Some(level) = weapon.chain(passive).choose(&mut thread_rng()) {
+            *level += 1;
+        }
+    }
+}
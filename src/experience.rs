@@ -0,0 +1,170 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    pause::PlayState,
+    ron_loader::RonAssetApp,
+    stats::{ModifierOp, ModifierSource, Stat, StatModifier, Stats},
+    Player,
+};
+
+/// Levels the player up along an experience curve.
+pub struct ExperiencePlugin;
+impl Plugin for ExperiencePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_ron_asset::<ExperienceCurve>(&["experience.ron"]);
+        app.add_systems(Startup, load_curve);
+        app.add_systems(Update, level_up.run_if(in_state(PlayState::Running)));
+    }
+}
+
+/// Experience needed for each level, loaded from a `.experience.ron` file.
+///
+/// Each segment applies from its `from_level` until the next segment starts, so the curve
+/// can steepen at breakpoints.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct ExperienceCurve(pub Vec<CurveSegment>);
+
+/// Needs `base + linear * n + quadratic * n²` experience to level up, where `n` is the
+/// number of levels past `from_level`.
+#[derive(Deserialize, Debug)]
+pub struct CurveSegment {
+    pub from_level: u32,
+    pub base: u64,
+    #[serde(default)]
+    pub linear: u64,
+    #[serde(default)]
+    pub quadratic: u64,
+}
+
+impl ExperienceCurve {
+    /// Experience needed to go from `level` to the next. Never zero, so a curve with a zero
+    /// `base` can't level the player up forever.
+    pub fn required(&self, level: u32) -> u64 {
+        let Some(segment) = self
+            .0
+            .iter()
+            .filter(|segment| segment.from_level <= level)
+            .max_by_key(|segment| segment.from_level)
+        else {
+            return u64::MAX;
+        };
+        let n = (level - segment.from_level) as u64;
+        (segment.base + segment.linear * n + segment.quadratic * n * n).max(1)
+    }
+
+    /// Spends `experience` on as many levels past `level` as it pays for, returning how many
+    /// levels that is and the experience left over.
+    pub fn level_ups(&self, level: u32, mut experience: u64) -> (u32, u64) {
+        let mut gained = 0;
+        while experience >= self.required(level + gained) {
+            experience -= self.required(level + gained);
+            gained += 1;
+        }
+        (gained, experience)
+    }
+}
+
+/// Granted for every level gained, on top of the chosen upgrade.
+const LEVEL_UP_BONUS: StatModifier = StatModifier {
+    stat: Stat::MaxHealth,
+    op: ModifierOp::Add(1.0),
+};
+
+#[derive(Resource)]
+struct Curve(Handle<ExperienceCurve>);
+
+fn load_curve(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.insert_resource(Curve(assets.load("player.experience.ron")));
+}
+
+/// Spends experience on as many levels as it pays for, then opens the upgrade choice for
+/// each of them in turn.
+fn level_up(
+    mut player: Query<(&mut Player, &mut Stats)>,
+    curve: Res<Curve>,
+    curves: Res<Assets<ExperienceCurve>>,
+    mut next_state: ResMut<NextState<PlayState>>,
+) {
+    let (Ok((mut player, mut stats)), Some(curve)) =
+        (player.get_single_mut(), curves.get(&curve.0))
+    else {
+        return;
+    };
+    let (gained, experience) = curve.level_ups(player.level, player.experience);
+    player.experience = experience;
+    player.level += gained;
+    player.pending_level_ups += gained;
+    for _ in 0..gained {
+        stats.add_modifier(ModifierSource::LevelUp, LEVEL_UP_BONUS);
+    }
+    player.next_level = curve.required(player.level);
+    if player.pending_level_ups > 0 {
+        next_state.set(PlayState::LevelUp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve() -> ExperienceCurve {
+        ExperienceCurve(vec![
+            CurveSegment {
+                from_level: 1,
+                base: 100,
+                linear: 50,
+                quadratic: 0,
+            },
+            CurveSegment {
+                from_level: 5,
+                base: 1000,
+                linear: 100,
+                quadratic: 10,
+            },
+        ])
+    }
+
+    #[test]
+    fn required_follows_each_segment() {
+        let curve = curve();
+        assert_eq!(curve.required(1), 100);
+        assert_eq!(curve.required(4), 250);
+        assert_eq!(curve.required(5), 1000);
+        assert_eq!(curve.required(7), 1000 + 200 + 40);
+    }
+
+    #[test]
+    fn required_before_first_segment_is_unreachable() {
+        assert_eq!(curve().required(0), u64::MAX);
+    }
+
+    #[test]
+    fn level_ups_spends_exactly_enough() {
+        let curve = curve();
+        assert_eq!(curve.level_ups(1, 99), (0, 99));
+        assert_eq!(curve.level_ups(1, 100), (1, 0));
+    }
+
+    #[test]
+    fn level_ups_carries_over_several_levels() {
+        // Levels 1 to 4 take 100 + 150 + 200 + 250, and level 5 takes 1000.
+        let curve = curve();
+        assert_eq!(curve.level_ups(1, 700), (4, 0));
+        assert_eq!(curve.level_ups(1, 1699), (4, 999));
+        assert_eq!(curve.level_ups(1, 1700 + 1110), (6, 0));
+        assert_eq!(curve.level_ups(3, 450 + 1000), (3, 0));
+    }
+
+    #[test]
+    fn level_ups_with_free_levels_ends() {
+        let curve = ExperienceCurve(vec![CurveSegment {
+            from_level: 1,
+            base: 0,
+            linear: 0,
+            quadratic: 0,
+        }]);
+        assert_eq!(curve.level_ups(1, 0), (0, 0));
+        assert_eq!(curve.level_ups(1, 3), (3, 0));
+    }
+}
@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+use bevy_spritesheet_animation::component::SpritesheetAnimation;
+
+use crate::{
+    animations::{self, Direction},
+    damage::DamageDealt,
+    dash::Dashing,
+    weapons::WeaponFired,
+    GameState, Health, Player,
+};
+
+/// Lets the player see when they have been hurt and how long they are invulnerable for, and
+/// which way their weapons are firing.
+pub struct FeedbackPlugin;
+impl Plugin for FeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HurtVignette>();
+        app.add_systems(Startup, setup_vignette);
+        app.add_systems(OnExit(GameState::Playing), clear_feedback);
+        app.add_systems(
+            Update,
+            play_hurt_animation.run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(
+            Update,
+            play_cast_animation
+                .after(play_hurt_animation)
+                .run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(
+            Update,
+            blink_invulnerable.run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(Update, update_vignette.run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// Whether the screen edges glow red while the player is invulnerable.
+#[derive(Resource)]
+pub struct HurtVignette {
+    pub enabled: bool,
+}
+impl Default for HurtVignette {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Present on the player while the hurt animation plays, pausing the walk animations.
+#[derive(Component)]
+pub struct HurtAnimation {
+    timer: Timer,
+}
+
+/// Present on the player while the cast animation plays, turning them to face the way a
+/// weapon fired whichever way they are walking.
+#[derive(Component)]
+pub struct CastAnimation {
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct Vignette;
+
+/// Seconds the player is shown, then hidden, while blinking.
+const BLINK_INTERVAL: f32 = 0.1;
+const BLINK_ALPHA: f32 = 0.25;
+const VIGNETTE_COLOUR: Color = Color::srgba(0.8, 0.0, 0.0, 0.5);
+const VIGNETTE_WIDTH: Val = Val::Px(48.0);
+/// Seconds the player holds the cast pose after a weapon fires.
+const CAST_DURATION: f32 = 0.4;
+
+fn play_hurt_animation(
+    mut commands: Commands,
+    mut damage_dealt: EventReader<DamageDealt>,
+    mut player: Query<(Entity, &Player, Option<&mut HurtAnimation>)>,
+    time: Res<Time>,
+) {
+    let Ok((entity, player, hurt)) = player.get_single_mut() else {
+        return;
+    };
+    if damage_dealt.read().any(|hit| hit.target == entity) {
+        let mut entity = commands.entity(entity);
+        entity.remove::<CastAnimation>().insert(HurtAnimation {
+            timer: Timer::from_seconds(0.5, TimerMode::Once),
+        });
+        if let Some(animation) = player.animations.get(animations::HURT, player.direction()) {
+            entity.insert(SpritesheetAnimation::from_id(animation));
+        }
+    } else if let Some(mut hurt) = hurt {
+        hurt.timer.tick(time.delta());
+        if hurt.timer.finished() {
+            commands.entity(entity).remove::<HurtAnimation>();
+        }
+    }
+}
+
+/// Casting is cut short by being hurt or walking, and never interrupts the hurt animation.
+pub fn play_cast_animation(
+    mut commands: Commands,
+    mut weapon_fired: EventReader<WeaponFired>,
+    mut player: Query<(Entity, &Player, Option<&mut CastAnimation>), Without<HurtAnimation>>,
+    time: Res<Time>,
+) {
+    let fired = weapon_fired.read().last();
+    let Ok((entity, player, cast)) = player.get_single_mut() else {
+        return;
+    };
+    let animation = fired
+        .and_then(|fired| Direction::from_vector(fired.direction))
+        .and_then(|direction| player.animations.get(animations::CAST, direction));
+    if let Some(animation) = animation {
+        commands.entity(entity).insert((
+            CastAnimation {
+                timer: Timer::from_seconds(CAST_DURATION, TimerMode::Once),
+            },
+            SpritesheetAnimation::from_id(animation),
+        ));
+    } else if let Some(mut cast) = cast {
+        cast.timer.tick(time.delta());
+        if cast.timer.finished() {
+            commands.entity(entity).remove::<CastAnimation>();
+        }
+    }
+}
+
+fn blink_invulnerable(mut player: Query<(&Health, &mut Sprite), With<Player>>) {
+    for (health, mut sprite) in player.iter_mut() {
+        let blinking = health.invulnerability_timer.as_ref().is_some_and(|timer| {
+            !timer.finished() && ((timer.elapsed_secs() / BLINK_INTERVAL) as u32) % 2 == 0
+        });
+        let alpha = if blinking { BLINK_ALPHA } else { 1.0 };
+        sprite.color.set_alpha(alpha);
+    }
+}
+
+fn setup_vignette(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                border: UiRect::all(VIGNETTE_WIDTH),
+                ..default()
+            },
+            border_color: Color::NONE.into(),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Vignette,
+    ));
+}
+
+/// Fades the vignette out over the player's invulnerability.
+fn update_vignette(
+    settings: Res<HurtVignette>,
+    // Dodging isn't being hurt, so the invulnerability of a dash doesn't count.
+    player: Query<&Health, (With<Player>, Without<Dashing>)>,
+    mut vignette: Query<(&mut BorderColor, &mut Visibility), With<Vignette>>,
+) {
+    let Ok((mut border, mut visibility)) = vignette.get_single_mut() else {
+        return;
+    };
+    let remaining = match player
+        .get_single()
+        .map(|health| &health.invulnerability_timer)
+    {
+        Ok(Some(timer)) if settings.enabled && !timer.finished() => timer.fraction_remaining(),
+        _ => 0.0,
+    };
+    if remaining > 0.0 {
+        *visibility = Visibility::Inherited;
+        border.0 = VIGNETTE_COLOUR.with_alpha(VIGNETTE_COLOUR.alpha() * remaining);
+    } else {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+/// Stops the blink and vignette from freezing on screen once gameplay stops.
+fn clear_feedback(
+    mut player: Query<&mut Sprite, With<Player>>,
+    mut vignette: Query<&mut Visibility, With<Vignette>>,
+) {
+    for mut sprite in player.iter_mut() {
+        sprite.color.set_alpha(1.0);
+    }
+    for mut visibility in vignette.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+}
@@ -1,14 +1,17 @@
 use bevy::{
-    core::FrameCount,
     prelude::*,
     render::{render_asset::RenderAssetUsages, texture::ImageSampler},
     tasks::{futures_lite::future, AsyncComputeTaskPool, Task},
 };
+use bevy_rapier2d::geometry::{Collider, CollisionGroups};
 use image::{Pixel, Rgba};
 use noise::{Abs, Exponent, Fbm, MultiFractal, NoiseFn, Perlin};
 use rayon::prelude::*;
 
-use crate::GameState;
+use crate::{
+    run::{RunEntity, RunSeed},
+    GameState, PLAYER_GROUP, PROJECTILE_GROUP, TERRAIN_GROUP,
+};
 
 #[derive(Component)]
 struct Chunk {
@@ -27,35 +30,39 @@ const X_EXTENT: f64 = SIZE_BOUND - -SIZE_BOUND;
 const Y_EXTENT: f64 = SIZE_BOUND - -SIZE_BOUND;
 const X_STEP: f64 = X_EXTENT / SIZE as f64;
 const Y_STEP: f64 = Y_EXTENT / SIZE as f64;
-const SEED: u32 = 1928877623;
+/// Radius in pixels around the origin kept free of solid terrain so the player never spawns stuck.
+const SPAWN_CLEARANCE: i32 = 12;
 
 impl Plugin for GenerationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, start_level);
+        app.add_systems(OnEnter(GameState::Playing), start_level);
         app.add_systems(Update, chunk_generated.run_if(in_state(GameState::Playing)));
     }
 }
-async fn gen_chunk(chunk_pos: IVec2) -> Option<ChunkGenerationResult> {
+async fn gen_chunk(chunk_pos: IVec2, seed: u32) -> Option<ChunkGenerationResult> {
     //let duration = Duration::from_secs_f32(rand::thread_rng().gen_range(0.05..5.0));
     //async_std::task::sleep(duration).await;
 
     let elevation =
-        Abs::new(Exponent::new(Fbm::<Perlin>::new(SEED).set_frequency(0.8)).set_exponent(2.0));
-    let moisture = Exponent::new(Fbm::<Perlin>::new(SEED)).set_exponent(0.5);
-    let tint = Abs::new(Fbm::<Perlin>::new(SEED));
+        Abs::new(Exponent::new(Fbm::<Perlin>::new(seed).set_frequency(0.8)).set_exponent(2.0));
+    let moisture = Exponent::new(Fbm::<Perlin>::new(seed)).set_exponent(0.5);
+    let tint = Abs::new(Fbm::<Perlin>::new(seed));
     let global_chunk_pos = chunk_pos * CHUNK_SIZE as i32;
+    let biome_at = |global_x: i32, global_y: i32| {
+        let current_x = -SIZE_BOUND + X_STEP * global_x as f64;
+        let current_y = -SIZE_BOUND + Y_STEP * global_y as f64;
+        let e = elevation.get([current_x, current_y]) as f32;
+        let m = moisture.get([current_x, current_y]) as f32;
+        biome(e, m)
+    };
     let mut texture = image::RgbaImage::new(CHUNK_SIZE as u32, CHUNK_SIZE as u32);
     texture
         .par_enumerate_pixels_mut()
         .for_each(|(x, y, pixel)| {
             let global_x = x as i32 + global_chunk_pos.x;
-            let current_x = -SIZE_BOUND + X_STEP * global_x as f64;
             let global_y = y as i32 + global_chunk_pos.y;
-            let current_y = -SIZE_BOUND + Y_STEP * global_y as f64;
-            let e = elevation.get([current_x, current_y]) as f32;
-            let m = moisture.get([current_x, current_y]) as f32;
             let tint = tint.get([global_x as f64, global_y as f64]) as f32;
-            let mut color = biome(e, m).color();
+            let mut color = biome_at(global_x, global_y).color();
             color.blend(&image::Rgba([
                 (tint * 255.0) as u8,
                 (tint * 255.0) as u8,
@@ -65,7 +72,35 @@ async fn gen_chunk(chunk_pos: IVec2) -> Option<ChunkGenerationResult> {
             *pixel = color
         });
     image::imageops::flip_vertical_in_place(&mut texture);
-    Some(ChunkGenerationResult::new(texture))
+
+    // Merge each row of solid pixels into runs so a chunk needs as few colliders as possible.
+    let half_chunk = CHUNK_SIZE as f32 * SCALE / 2.0;
+    let mut solid = Vec::new();
+    for y in 0..CHUNK_SIZE as i32 {
+        let mut run_start = None;
+        for x in 0..=CHUNK_SIZE as i32 {
+            let global = IVec2::new(x, y) + global_chunk_pos;
+            let is_solid = x < CHUNK_SIZE as i32
+                && global.length_squared() > SPAWN_CLEARANCE * SPAWN_CLEARANCE
+                && biome_at(global.x, global.y).solid();
+            match (run_start, is_solid) {
+                (None, true) => run_start = Some(x),
+                (Some(start), false) => {
+                    let length = (x - start) as f32;
+                    solid.push((
+                        Vec2::new(
+                            (start as f32 + length / 2.0) * SCALE - half_chunk,
+                            (y as f32 + 0.5) * SCALE - half_chunk,
+                        ),
+                        Vec2::new(length * SCALE / 2.0, SCALE / 2.0),
+                    ));
+                    run_start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    Some(ChunkGenerationResult::new(texture, solid))
 }
 enum Biome {
     Ocean,
@@ -137,6 +172,10 @@ fn biome(e: f32, m: f32) -> Biome {
     Biome::TropicalRainForest
 }
 impl Biome {
+    /// Whether this biome blocks movement and projectiles.
+    fn solid(&self) -> bool {
+        matches!(self, Biome::Ocean)
+    }
     fn color(self) -> image::Rgba<u8> {
         match self {
             Biome::Ocean => image::Rgba([68, 68, 122, 255]),
@@ -182,6 +221,20 @@ fn chunk_generated(
                 },
                 materials.add(texture),
             ));
+            if !result.solid.is_empty() {
+                entity_commands.insert((
+                    Terrain,
+                    Collider::compound(
+                        result
+                            .solid
+                            .into_iter()
+                            .map(|(centre, half)| (centre, 0.0, Collider::cuboid(half.x, half.y)))
+                            .collect(),
+                    ),
+                    // The player only ever meets terrain through their dash's shape cast.
+                    CollisionGroups::new(TERRAIN_GROUP, PROJECTILE_GROUP | PLAYER_GROUP),
+                ));
+            }
             entity_commands.remove::<GeneratingChunk>();
         }
     }
@@ -189,35 +242,40 @@ fn chunk_generated(
 
 struct ChunkGenerationResult {
     texture: image::ImageBuffer<Rgba<u8>, Vec<u8>>,
+    /// Centre and half extents of each solid rectangle, relative to the chunk.
+    solid: Vec<(Vec2, Vec2)>,
 }
 impl ChunkGenerationResult {
-    fn new(texture: image::ImageBuffer<Rgba<u8>, Vec<u8>>) -> Self {
-        Self { texture }
+    fn new(texture: image::ImageBuffer<Rgba<u8>, Vec<u8>>, solid: Vec<(Vec2, Vec2)>) -> Self {
+        Self { texture, solid }
     }
 }
+
+/// Marks chunks whose colliders block projectiles.
+#[derive(Component)]
+pub struct Terrain;
 #[derive(Component)]
 struct GeneratingChunk {
     generation_task: Task<Option<ChunkGenerationResult>>,
 }
 
-fn start_level(frames: Res<FrameCount>, mut commands: Commands) {
-    if frames.0 == 10 {
-        let thread_pool = AsyncComputeTaskPool::get();
-        for (x, y) in spiral::ChebyshevIterator::new(0, 0, SPAWN_CHUNKS) {
-            let pos = IVec2::new(x, y);
-            let task = thread_pool.spawn(gen_chunk(pos));
-            commands
-                .spawn(Chunk { pos })
-                .insert(SpatialBundle::from_transform(Transform::from_translation(
-                    Vec2::new(
-                        (x * CHUNK_SIZE as i32) as f32 * SCALE,
-                        (y * CHUNK_SIZE as i32) as f32 * SCALE,
-                    )
-                    .extend(0.0),
-                )))
-                .insert(GeneratingChunk {
-                    generation_task: task,
-                });
-        }
+fn start_level(mut commands: Commands, seed: Res<RunSeed>) {
+    let thread_pool = AsyncComputeTaskPool::get();
+    for (x, y) in spiral::ChebyshevIterator::new(0, 0, SPAWN_CHUNKS) {
+        let pos = IVec2::new(x, y);
+        let task = thread_pool.spawn(gen_chunk(pos, seed.0));
+        commands
+            .spawn(Chunk { pos })
+            .insert(SpatialBundle::from_transform(Transform::from_translation(
+                Vec2::new(
+                    (x * CHUNK_SIZE as i32) as f32 * SCALE,
+                    (y * CHUNK_SIZE as i32) as f32 * SCALE,
+                )
+                .extend(0.0),
+            )))
+            .insert(GeneratingChunk {
+                generation_task: task,
+            })
+            .insert(RunEntity);
     }
 }
@@ -2,7 +2,7 @@ use bevy::prelude::*;
 use bevy_ineffable::{config::simple_asset_loading::MergeMode, prelude::*};
 use bevy_spritesheet_animation::component::SpritesheetAnimation;
 
-use crate::{GameState, Player, PlayerAnimation};
+use crate::{animations::{self, Direction}, dash::Dashing, feedback::{play_cast_animation, CastAnimation, HurtAnimation}, pause::PlayState, stats::{Stat, Stats}, Player};
 
 pub struct InputPlugin;
 impl Plugin for InputPlugin {
@@ -11,9 +11,8 @@ impl Plugin for InputPlugin {
         app.register_input_action::<PlayerInput>();
         app.register_input_action::<MenuInput>();
         app.add_systems(Startup, init);
-        app.add_systems(Update, start_playing.run_if(in_state(GameState::StartScreen)));
-        app.add_systems(Update, player_movement.run_if(in_state(GameState::Playing)));
-        app.add_systems(Update, player_rotate.run_if(in_state(GameState::Playing)));
+        app.add_systems(Update, player_movement.after(play_cast_animation).run_if(in_state(PlayState::Running)));
+        app.add_systems(Update, player_rotate.run_if(in_state(PlayState::Running)));
     }
 }
 #[derive(InputAction)]
@@ -23,16 +22,16 @@ pub enum MenuInput {
     /// enacted instantaneously, so we'll define it as a pulse.
     #[ineffable(pulse)]
     Accept,
+    #[ineffable(pulse)]
+    Up,
+    #[ineffable(pulse)]
+    Down,
+    /// Opens and closes the pause menu.
+    #[ineffable(pulse)]
+    Pause,
     // You can add more actions here...
 }
 
-fn start_playing(mut next_state: ResMut<NextState<GameState>>, bindings: Res<Ineffable>,){
-    if bindings.just_pulsed(ineff!(MenuInput::Accept)) {
-        info!("Accept Pressed");
-        next_state.set(GameState::Playing);
-    }
-}
-
 #[derive(InputAction)]
 pub enum PlayerInput {
     /// In this example, the only thing the player can do is honk.
@@ -42,9 +41,11 @@ pub enum PlayerInput {
     Move,
     #[ineffable(dual_axis)]
     Face,
+    /// Bursts a short way in the direction of movement.
+    #[ineffable(pulse)]
+    Dash,
     // You can add more actions here...
 }
-const SPEED: f32 = 72.0;
 /// Speed at which the player is rotated.
 /// Value is negative because it feels more natural.
 //const ROTATE_SPEED: f32 = -3.0;
@@ -55,43 +56,31 @@ fn player_movement(
     mut commands: Commands,
     bindings: Res<Ineffable>,
     time: Res<Time>,
-    mut query: Query<(Entity, &mut Transform, &Player)>,
+    mut query: Query<(Entity, &mut Transform, &Player, &Stats, Has<HurtAnimation>, Has<CastAnimation>), Without<Dashing>>,
 ) {
-    if let Ok((entity, mut transform, player)) = query.get_single_mut() {
+    if let Ok((entity, mut transform, player, stats, hurt, casting)) = query.get_single_mut() {
         let movement_direction = bindings.direction_2d(ineff!(PlayerInput::Move));
-        transform.translation.x += movement_direction.x * time.delta_seconds() * SPEED;
-        transform.translation.y += movement_direction.y * time.delta_seconds() * SPEED;
-        //let angle = Vec2::X.dot(player.facing).acos().to_degrees();
-        let angle = (player.facing + 180.0) % 360.0;
+        let speed = stats.get(Stat::MoveSpeed);
+        transform.translation.x += movement_direction.x * time.delta_seconds() * speed;
+        transform.translation.y += movement_direction.y * time.delta_seconds() * speed;
+        // Let the hurt animation play out before walking again.
+        if hurt {
+            return;
+        }
         let mut player_entity = commands.entity(entity);
-        let moving = movement_direction.length() > 0.0;
-        let animation = if (45.0..135.0).contains(&angle) {
-            if moving {
-                &PlayerAnimation::WalkLeft
-            } else {
-                &PlayerAnimation::IdleLeft
-            }
-        } else if (135.0..225.0).contains(&angle) {
-            if moving {
-                &PlayerAnimation::WalkDown
-            } else {
-                &PlayerAnimation::IdleDown
+        // Walk the way the player is moving, but stand facing the way they are aiming. Walking
+        // cuts the cast pose short, so fast weapons don't stop the walk from ever showing.
+        let (state, direction) = match Direction::from_vector(movement_direction) {
+            Some(direction) => {
+                player_entity.remove::<CastAnimation>();
+                (animations::WALK, direction)
             }
-        } else if (225.0..315.0).contains(&angle) {
-            if moving {
-                &PlayerAnimation::WalkRight
-            } else {
-                &PlayerAnimation::IdleRight
-            }
-        } else if moving {
-            &PlayerAnimation::WalkUp
-        } else {
-            &PlayerAnimation::IdleUp
+            None if casting => return,
+            None => (animations::IDLE, player.direction()),
         };
-
-        player_entity.insert(SpritesheetAnimation::from_id(
-            *player.animations.get(animation).unwrap(),
-        ));
+        if let Some(animation) = player.animations.get(state, direction) {
+            player_entity.insert(SpritesheetAnimation::from_id(animation));
+        }
     }
 }
 
@@ -5,29 +5,70 @@ use std::time::Duration;
 
 use bevy::{
     //core::FrameCount,
-    log::LogPlugin, prelude::*, render::texture::{ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor}, utils::HashMap
+    log::LogPlugin, prelude::*,
 };
 use bevy_rapier2d::prelude::*;
 use bevy_spritesheet_animation::{
-    animation::AnimationId, component::SpritesheetAnimation, library::SpritesheetLibrary,
-    plugin::SpritesheetAnimationPlugin, spritesheet::Spritesheet,
+    component::SpritesheetAnimation, plugin::SpritesheetAnimationPlugin,
 };
-use projectiles::PureProjectileSkill;
+use animations::{AnimatedSheets, Direction, SpriteAnimations};
+use characters::{CharacterDefinition, SelectedCharacter};
+use damage::{Armour, DamageBuffer};
+use dash::Dash;
+use passives::PassiveItems;
+use run::{RunEntity, RunMode};
+use stats::{Stat, Stats, StatusEffects};
+use weapons::{Weapon, Weapons};
 
+mod animations;
+mod characters;
+mod damage;
+mod damage_numbers;
+mod dash;
+mod drops;
 mod enemies;
+mod evolution;
+mod experience;
+mod feedback;
 mod generation;
 mod input;
+mod menu;
+mod meta;
+mod passives;
+mod pause;
 mod pickups;
 mod projectiles;
+mod ron_loader;
+mod run;
+mod stats;
+mod upgrades;
+mod weapons;
 fn main() {
     let mut app = App::new();
     app.add_plugins(DefaultPlugins.set(LogPlugin{level:bevy::log::Level::DEBUG,..default()}));
     app.add_plugins(SpritesheetAnimationPlugin);
+    app.add_plugins(animations::AnimationsPlugin);
+    app.add_plugins(damage::DamagePlugin);
+    app.add_plugins(damage_numbers::DamageNumbersPlugin);
+    app.add_plugins(feedback::FeedbackPlugin);
+    app.add_plugins(menu::MenuPlugin);
+    app.add_plugins(run::RunPlugin);
+    app.add_plugins(characters::CharactersPlugin);
+    app.add_plugins(meta::MetaPlugin);
+    app.add_plugins(pause::PausePlugin);
     app.add_plugins(input::InputPlugin);
+    app.add_plugins(dash::DashPlugin);
     app.add_plugins(generation::GenerationPlugin);
     app.add_plugins(projectiles::ProjectilesPlugin);
+    app.add_plugins(weapons::WeaponsPlugin);
+    app.add_plugins(evolution::EvolutionPlugin);
+    app.add_plugins(experience::ExperiencePlugin);
+    app.add_plugins(stats::StatsPlugin);
+    app.add_plugins(passives::PassivesPlugin);
+    app.add_plugins(upgrades::UpgradesPlugin);
     app.add_plugins(enemies::EnemiesPlugin);
     app.add_plugins(pickups::PickupsPlugin);
+    app.add_plugins(drops::DropsPlugin);
     app.add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(
         generation::SCALE,
     ));
@@ -38,18 +79,17 @@ fn main() {
     });
     app.insert_state(GameState::StartScreen);
     app.add_systems(Startup, setup_graphics);
-    app.add_systems(Startup, setup_start_screen);
+    app.add_systems(OnEnter(GameState::StartScreen), setup_start_screen);
     app.add_systems(OnExit(GameState::StartScreen), teardown_start_screen);
     app.add_systems(OnEnter(GameState::Playing), setup_character);
-    app.add_systems(Update, apply_damage.run_if(in_state(GameState::Playing)));
     app.add_systems(Update, despawn_dead.run_if(in_state(GameState::Playing)));
     app.add_systems(Update, end_level.run_if(in_state(GameState::Playing)));
+    app.add_event::<RuntimeEnded>();
     app.add_systems(
         Update,
         update_health_bars.run_if(in_state(GameState::Playing)),
     );
     app.add_systems(Update, update_exp_bars.run_if(in_state(GameState::Playing)));
-    app.add_systems(Update, level_up.run_if(in_state(GameState::Playing)));
     app.run();
 }
 
@@ -105,9 +145,16 @@ struct StartScreen;
 enum GameState {
     #[default]
     StartScreen,
+    /// Choosing who to play as before a run.
+    CharacterSelect,
     Playing,
-    #[allow(dead_code)]
+    /// The world is frozen while the player's death animation plays.
+    Dying,
     DeathScreen,
+    /// Results of a run that lasted until the end and defeated the reaper.
+    Victory,
+    /// Passed through for a frame to tear down a run before starting the next.
+    Restarting,
 }
 
 fn setup_graphics(mut commands: Commands) {
@@ -130,177 +177,107 @@ fn setup_graphics(mut commands: Commands) {
 struct Level {
     runtime: Timer,
 }
-fn end_level(time: Res<Time>, mut level: ResMut<Level>) {
+/// Sent once, when the level's runtime is up.
+#[derive(Event)]
+struct RuntimeEnded;
+
+fn end_level(
+    time: Res<Time>,
+    mut level: ResMut<Level>,
+    mut runtime_ended: EventWriter<RuntimeEnded>,
+) {
     level.runtime.tick(time.delta());
+    if level.runtime.just_finished() {
+        runtime_ended.send(RuntimeEnded);
+    }
 }
 
 fn setup_character(
     //frames: Res<FrameCount>,
     mut commands: Commands,
-    mut library: ResMut<SpritesheetLibrary>,
-    mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut sheets: AnimatedSheets,
     mut camera: Query<Entity, With<Camera>>,
     assets: Res<AssetServer>,
+    run_mode: Res<RunMode>,
+    selected: Res<SelectedCharacter>,
+    characters: Res<Assets<CharacterDefinition>>,
 ) {
     //if frames.0 == 10 {
     // Create an animation
     commands.insert_resource(Level {
-        runtime: Timer::from_seconds(15.0 * 60.0, TimerMode::Once),
-    });
-
-    let sheet = Spritesheet::new(13, 46);
-    let idle_down_clip = library.new_clip(|clip| {
-        clip.push_frame_indices(sheet.row_partial(10, 0..2));
-        clip.push_frame_indices(sheet.row_partial(10, 5..6));
-        clip.set_default_duration(
-            bevy_spritesheet_animation::animation::AnimationDuration::PerCycle(5000),
-        );
-    });
-    let idle_down_animation = library.new_animation(|animation| {
-        animation.add_stage(idle_down_clip.into());
-    });
-    let idle_right_clip = library.new_clip(|clip| {
-        clip.push_frame_indices(sheet.row_partial(11, 0..1));
-        clip.push_frame_indices(sheet.row_partial(11, 8..9));
-        clip.push_frame_indices(sheet.row_partial(7, 1..2));
-        clip.set_default_duration(
-            bevy_spritesheet_animation::animation::AnimationDuration::PerCycle(5000),
-        );
-    });
-    let idle_right_animation = library.new_animation(|animation| {
-        animation.add_stage(idle_right_clip.into());
-    });
-    let idle_up_clip = library.new_clip(|clip| {
-        clip.push_frame_indices(sheet.row_partial(8, 0..2));
-        clip.push_frame_indices(sheet.row_partial(8, 5..6));
-        clip.set_default_duration(
-            bevy_spritesheet_animation::animation::AnimationDuration::PerCycle(5000),
-        );
-    });
-    let idle_up_animation = library.new_animation(|animation| {
-        animation.add_stage(idle_up_clip.into());
-    });
-    let idle_left_clip = library.new_clip(|clip| {
-        clip.push_frame_indices(sheet.row_partial(9, 0..1));
-        clip.push_frame_indices(sheet.row_partial(9, 8..9));
-        clip.push_frame_indices(sheet.row_partial(5, 1..2));
-        clip.set_default_duration(
-            bevy_spritesheet_animation::animation::AnimationDuration::PerCycle(5000),
-        );
-    });
-    let idle_left_animation = library.new_animation(|animation| {
-        animation.add_stage(idle_left_clip.into());
-    });
-    let walk_down_clip = library.new_clip(|clip| {
-        clip.push_frame_indices(sheet.row_partial(10, 0..9));
-    });
-    let walk_down_animation = library.new_animation(|animation| {
-        animation.add_stage(walk_down_clip.into());
-    });
-    let walk_right_clip = library.new_clip(|clip| {
-        clip.push_frame_indices(sheet.row_partial(11, 0..9));
-    });
-    let walk_right_animation = library.new_animation(|animation| {
-        animation.add_stage(walk_right_clip.into());
-    });
-    let walk_up_clip = library.new_clip(|clip| {
-        clip.push_frame_indices(sheet.row_partial(8, 0..9));
-    });
-    let walk_up_animation = library.new_animation(|animation| {
-        animation.add_stage(walk_up_clip.into());
-    });
-    let walk_left_clip = library.new_clip(|clip| {
-        clip.push_frame_indices(sheet.row_partial(9, 0..9));
-    });
-    let walk_left_animation = library.new_animation(|animation| {
-        animation.add_stage(walk_left_clip.into());
+        runtime: match *run_mode {
+            RunMode::Timed => Timer::from_seconds(15.0 * 60.0, TimerMode::Once),
+            // Never finishes.
+            RunMode::Endless => Timer::new(Duration::MAX, TimerMode::Once),
+        },
     });
-    // Spawn a sprite using Bevy's built-in SpriteSheetBundle
-
-    let texture =
-        assets.load_with_settings("character.png", |s: &mut ImageLoaderSettings| match &mut s
-            .sampler
-        {
-            ImageSampler::Default => s.sampler = ImageSampler::nearest(),
-            ImageSampler::Descriptor(sampler) => {
-                *sampler = ImageSamplerDescriptor::nearest();
-            }
-        });
 
-    let layout = atlas_layouts.add(TextureAtlasLayout::from_grid(
-        UVec2::new(64, 64),
-        13,
-        46,
-        None,
-        None,
-    ));
-    let mut player = Player::default();
-    player
-        .animations
-        .insert(PlayerAnimation::IdleDown, idle_down_animation);
-    player
-        .animations
-        .insert(PlayerAnimation::IdleRight, idle_right_animation);
-    player
+    let character = characters
+        .get(&selected.0)
+        .expect("a character is chosen before playing");
+    let sheet = sheets
+        .get(&character.animations)
+        .expect("a character's sprites load before it can be chosen");
+    let idle_down_animation = sheet
         .animations
-        .insert(PlayerAnimation::IdleUp, idle_up_animation);
-    player
-        .animations
-        .insert(PlayerAnimation::IdleLeft, idle_left_animation);
-    player
-        .animations
-        .insert(PlayerAnimation::WalkDown, walk_down_animation);
-    player
-        .animations
-        .insert(PlayerAnimation::WalkRight, walk_right_animation);
-    player
-        .animations
-        .insert(PlayerAnimation::WalkUp, walk_up_animation);
-    player
-        .animations
-        .insert(PlayerAnimation::WalkLeft, walk_left_animation);
-    player.next_level = 500;
+        .get(animations::IDLE, Direction::Down)
+        .unwrap();
+    let mut player = Player {
+        animations: sheet.animations,
+        ..default()
+    };
+    player.level = 1;
+    let stats = Stats::new(character.stats.clone());
+    let max_health = stats.get(Stat::MaxHealth) as u32;
+    let pickup_radius = stats.get(Stat::PickupRadius);
     let player_id = commands
         .spawn((
             player,
             SpriteBundle {
-                texture,
+                texture: sheet.texture,
+                sprite: Sprite {
+                    color: character.tint,
+                    ..default()
+                },
                 transform: Transform::from_xyz(0., 0., 2.),
                 ..default()
             },
             TextureAtlas {
-                layout,
+                layout: sheet.layout,
                 ..default()
             },
             Collider::cuboid(16.0, 32.0),
             Sensor,
             Health {
-                current: 20,
-                max: 20,
+                current: max_health,
+                max: max_health,
                 invulnerability_timer: None,
                 invulnerability_duration: Duration::from_secs(2),
             },
             CollisionGroups::new(PLAYER_GROUP, ENEMY_GROUP | crate::PICKUP_GROUP),
             DamageBuffer::default(),
+            Armour(0),
             // Add a SpritesheetAnimation component that references our newly created animation
             SpritesheetAnimation::from_id(idle_down_animation),
-            projectiles::PureProjectileSkill {
-                cooldown: Timer::from_seconds(1.0, TimerMode::Repeating),
-            },
+            Weapons(vec![Weapon::new(assets.load(character.starting_weapon.clone()))]),
+            RunEntity,
+            PassiveItems::default(),
+            character.damage_roll,
+            stats,
         ))
-        .insert(ActiveEvents::COLLISION_EVENTS)
+        .insert((ActiveEvents::COLLISION_EVENTS, Dash::new(character.dash.clone()), StatusEffects::default()))
         .id();
     commands
         .spawn((
             TransformBundle::from_transform(Transform::from_translation(Vec3::ZERO)),
-            Collider::cuboid(12.0, 28.0),
+            Collider::cuboid(PLAYER_BODY.x, PLAYER_BODY.y),
             CollisionGroups::new(PLAYER_GROUP, ENEMY_GROUP),
         ))
         .set_parent(player_id);
     commands
         .spawn((
             TransformBundle::from_transform(Transform::from_translation(Vec3::ZERO)),
-            Collider::ball(48.0),
+            Collider::ball(pickup_radius),
             Sensor,
             pickups::PlayerPickup,
             ActiveEvents::COLLISION_EVENTS,
@@ -395,6 +372,9 @@ const PROJECTILE_GROUP: Group = Group::GROUP_2;
 const ENEMY_GROUP: Group = Group::GROUP_3;
 const PICKUP_GROUP: Group = Group::GROUP_4;
 const PLAYER_PICKUP_GROUP: Group = Group::GROUP_5;
+const TERRAIN_GROUP: Group = Group::GROUP_6;
+/// Half extents of the collider enemies bump into, and that dashes are stopped with.
+const PLAYER_BODY: Vec2 = Vec2::new(12.0, 28.0);
 
 #[derive(Component)]
 struct HealthBar(f32);
@@ -420,7 +400,7 @@ fn update_exp_bars(mut bars: Query<(&ExpBar, &mut Sprite, &Parent)>, health: Que
             return;
         };
         sprite.custom_size = Some(Vec2::new(
-            bar.0 * (health.experience as f32 / health.next_level as f32),
+            bar.0 * (health.experience as f32 / health.next_level.max(1) as f32),
             sprite.custom_size.unwrap().y,
         ))
     }
@@ -433,44 +413,6 @@ struct Health {
     invulnerability_timer: Option<Timer>,
     invulnerability_duration: Duration,
 }
-#[derive(Component, Default, Debug)]
-struct DamageBuffer(Vec<Damage>);
-#[derive(Debug)]
-struct Damage {
-    source: Entity,
-    amount: u32,
-}
-#[derive(Component)]
-struct DamageSource;
-fn apply_damage(
-    mut commands: Commands,
-    mut query: Query<(&mut DamageBuffer, &mut Health)>,
-    time: Res<Time>,
-) {
-    for (mut buffer, mut health) in query.iter_mut() {
-        if let Some(ref mut invuln) = &mut health.invulnerability_timer {
-            if !invuln.finished() {
-                invuln.tick(time.delta());
-                continue;
-            }
-        }
-        let mut took_damage = false;
-        buffer.0.retain_mut(|damage| {
-            if commands.get_entity(damage.source).is_some() {
-                took_damage = true;
-                info!("Taking {}", damage.amount);
-                health.current = health.current.saturating_sub(damage.amount);
-                true
-            } else {
-                false
-            }
-        });
-        if took_damage && health.invulnerability_duration > Duration::ZERO {
-            health.invulnerability_timer =
-                Some(Timer::new(health.invulnerability_duration, TimerMode::Once));
-        }
-    }
-}
 
 #[derive(Component)]
 struct Dead {
@@ -491,34 +433,28 @@ fn despawn_dead(mut commands: Commands, mut dead: Query<(Entity, &mut Dead)>, ti
     }
 }
 
-fn level_up(mut player: Query<(&mut Player, &mut PureProjectileSkill)>) {
-    if let Ok((mut player, mut skill)) = player.get_single_mut() {
-        if player.experience >= player.next_level {
-            player.experience = player.experience.saturating_sub(player.next_level);
-            skill.cooldown = Timer::from_seconds(
-                skill.cooldown.duration().as_secs_f32() * 0.8,
-                TimerMode::Repeating,
-            );
-        }
-    }
-}
-
 #[derive(Component, Default)]
 pub struct Player {
     facing: f32,
-    animations: HashMap<PlayerAnimation, AnimationId>,
+    animations: SpriteAnimations,
     pub experience: u64,
+    /// Experience needed for the next level.
     pub next_level: u64,
+    pub level: u32,
+    /// Levels gained that are still waiting for an upgrade to be chosen.
+    pub pending_level_ups: u32,
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Hash)]
-enum PlayerAnimation {
-    IdleRight,
-    IdleLeft,
-    IdleDown,
-    IdleUp,
-    WalkRight,
-    WalkLeft,
-    WalkDown,
-    WalkUp,
+impl Player {
+    /// Unit vector the player is aiming along.
+    fn aim(&self) -> Vec2 {
+        // Facing is measured so that 270 degrees on from it points along +X.
+        Vec2::from_angle((self.facing + 270.0).to_radians())
+    }
+
+    /// The way the player's sprite faces, from the angle they are aiming at.
+    fn direction(&self) -> Direction {
+        Direction::from_vector(self.aim()).unwrap_or(Direction::Down)
+    }
 }
+
@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+use bevy_ineffable::prelude::*;
+
+use crate::input::MenuInput;
+
+/// Full screen menus of text options, picked with [`MenuInput`].
+pub struct MenuPlugin;
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<MenuSelected>();
+        app.add_systems(Update, stack_menus.before(navigate_menus));
+        app.add_systems(Update, navigate_menus);
+        app.add_systems(Update, highlight_selected.after(navigate_menus));
+    }
+}
+
+#[derive(Component)]
+pub struct Menu {
+    selected: usize,
+    options: usize,
+    /// Menus opened later sit on top, and only the top one is navigated.
+    stacked: u64,
+}
+
+#[derive(Component)]
+struct MenuOption(usize);
+
+/// Sent when an option of `menu` is accepted.
+#[derive(Event)]
+pub struct MenuSelected {
+    pub menu: Entity,
+    pub option: usize,
+}
+
+const BACKGROUND: Color = Color::srgba(0.0, 0.0, 0.0, 0.75);
+const SELECTED_COLOUR: Color = Color::srgb(1.0, 0.85, 0.2);
+const OPTION_COLOUR: Color = Color::WHITE;
+
+/// Spawns a menu with a title, lines of information and options in that order,
+/// returning the root entity so the caller can tag and later despawn it.
+pub fn spawn_menu(
+    commands: &mut Commands,
+    title: &str,
+    lines: &[String],
+    options: &[&str],
+) -> Entity {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            background_color: BACKGROUND.into(),
+            // Draw over the rest of the UI.
+            z_index: ZIndex::Global(10),
+            ..default()
+        })
+        .insert(Menu {
+            selected: 0,
+            options: options.len(),
+            stacked: 0,
+        })
+        .with_children(|menu| {
+            menu.spawn(TextBundle::from_section(
+                title,
+                TextStyle {
+                    font_size: 64.0,
+                    ..default()
+                },
+            ));
+            for line in lines {
+                menu.spawn(TextBundle::from_section(
+                    line.clone(),
+                    TextStyle {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                ));
+            }
+            for (index, option) in options.iter().enumerate() {
+                menu.spawn(TextBundle::from_section(
+                    *option,
+                    TextStyle {
+                        font_size: 36.0,
+                        ..default()
+                    },
+                ))
+                .insert(MenuOption(index));
+            }
+        })
+        .id()
+}
+
+fn stack_menus(mut menus: Query<&mut Menu, Added<Menu>>, mut opened: Local<u64>) {
+    for mut menu in menus.iter_mut() {
+        *opened += 1;
+        menu.stacked = *opened;
+    }
+}
+
+fn navigate_menus(
+    bindings: Res<Ineffable>,
+    mut menus: Query<(Entity, &mut Menu)>,
+    mut selected: EventWriter<MenuSelected>,
+) {
+    let top = menus
+        .iter_mut()
+        .filter(|(_, menu)| menu.options > 0)
+        .max_by_key(|(_, menu)| menu.stacked);
+    if let Some((entity, mut menu)) = top {
+        if bindings.just_pulsed(ineff!(MenuInput::Up)) {
+            menu.selected = (menu.selected + menu.options - 1) % menu.options;
+        }
+        if bindings.just_pulsed(ineff!(MenuInput::Down)) {
+            menu.selected = (menu.selected + 1) % menu.options;
+        }
+        if bindings.just_pulsed(ineff!(MenuInput::Accept)) {
+            selected.send(MenuSelected {
+                menu: entity,
+                option: menu.selected,
+            });
+        }
+    }
+}
+
+fn highlight_selected(menus: Query<&Menu>, mut options: Query<(&MenuOption, &Parent, &mut Text)>) {
+    for (option, parent, mut text) in options.iter_mut() {
+        let Ok(menu) = menus.get(parent.get()) else {
+            continue;
+        };
+        text.sections[0].style.color = if menu.selected == option.0 {
+            SELECTED_COLOUR
+        } else {
+            OPTION_COLOUR
+        };
+    }
+}
@@ -0,0 +1,72 @@
+use std::{fs, path::Path};
+
+use bevy::{asset::ron, prelude::*, utils::HashSet};
+use serde::{Deserialize, Serialize};
+
+/// Progress that carries over between runs, saved next to the game.
+pub struct MetaPlugin;
+impl Plugin for MetaPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MetaProgress::load());
+    }
+}
+
+const SAVE_PATH: &str = "save/meta.ron";
+
+/// Unlocked by completing a run, and needed to play some characters.
+pub const COMPLETION_UNLOCK: &str = "completed";
+/// How many endless survival times are kept.
+const LEADERBOARD_SIZE: usize = 10;
+
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct MetaProgress {
+    pub runs_completed: u32,
+    pub unlocked: HashSet<String>,
+    /// Seconds survived in the best endless runs, longest first.
+    pub endless_times: Vec<u64>,
+    /// Collected over every run.
+    pub gold: u64,
+}
+
+impl MetaProgress {
+    /// Reads the save file, starting afresh if there isn't one or it can't be read.
+    fn load() -> Self {
+        let Ok(contents) = fs::read_to_string(SAVE_PATH) else {
+            return Self::default();
+        };
+        ron::from_str(&contents).unwrap_or_else(|error| {
+            warn!("Could not read {SAVE_PATH}: {error}");
+            Self::default()
+        })
+    }
+
+    pub fn is_unlocked(&self, unlock: &str) -> bool {
+        self.unlocked.contains(unlock)
+    }
+
+    /// Adds an endless run to the leaderboard, returning its place if it made it on.
+    pub fn record_endless_time(&mut self, seconds: u64) -> Option<usize> {
+        let place = self.endless_times.partition_point(|&time| time >= seconds);
+        if place >= LEADERBOARD_SIZE {
+            return None;
+        }
+        self.endless_times.insert(place, seconds);
+        self.endless_times.truncate(LEADERBOARD_SIZE);
+        Some(place)
+    }
+
+    pub fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, default())
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                if let Some(directory) = Path::new(SAVE_PATH).parent() {
+                    fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+                }
+                fs::write(SAVE_PATH, contents).map_err(|error| error.to_string())
+            });
+        if let Err(error) = result {
+            warn!("Could not save {SAVE_PATH}: {error}");
+        }
+    }
+}
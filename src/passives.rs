@@ -0,0 +1,158 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    ron_loader::RonAssetApp,
+    run::RunEntity,
+    stats::{ModifierSource, StatModifier, Stats},
+    upgrades::Rarity,
+    GameState, Player,
+};
+
+/// Items that do nothing but improve the player's stats, shown along the top of the screen.
+pub struct PassivesPlugin;
+impl Plugin for PassivesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_ron_asset::<PassiveDefinition>(&["passive.ron"]);
+        app.add_systems(OnEnter(GameState::Playing), setup_passive_hud);
+        app.add_systems(Update, apply_passives.run_if(in_state(GameState::Playing)));
+        app.add_systems(
+            Update,
+            update_passive_hud.run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// A passive item, loaded from a `.passive.ron` file.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct PassiveDefinition {
+    pub name: String,
+    pub max_level: u32,
+    /// How often the item is offered on level up.
+    #[serde(default)]
+    pub rarity: Rarity,
+    /// Applied once for every level of the item.
+    pub modifiers: Vec<StatModifier>,
+    /// Fills the item's HUD icon when it has no image.
+    pub colour: Color,
+    /// Asset path of the image shown on the HUD.
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+pub struct PassiveItem {
+    pub definition: Handle<PassiveDefinition>,
+    pub level: u32,
+}
+
+impl PassiveItem {
+    pub fn new(definition: Handle<PassiveDefinition>) -> Self {
+        Self {
+            definition,
+            level: 1,
+        }
+    }
+}
+
+/// The passive items the player is carrying, up to [`MAX_PASSIVES`].
+#[derive(Component, Default)]
+pub struct PassiveItems(pub Vec<PassiveItem>);
+
+pub const MAX_PASSIVES: usize = 6;
+
+impl PassiveItems {
+    /// Whether an item loaded from `path` is being carried.
+    pub fn contains_path(&self, path: &str) -> bool {
+        self.0.iter().any(|item| {
+            item.definition
+                .path()
+                .is_some_and(|item_path| item_path.path() == std::path::Path::new(path))
+        })
+    }
+}
+
+#[derive(Component)]
+struct PassiveHud;
+
+const ICON_SIZE: Val = Val::Px(32.0);
+
+/// Replaces the stat modifiers of every carried item whenever the items change.
+fn apply_passives(
+    mut players: Query<(&PassiveItems, &mut Stats), Changed<PassiveItems>>,
+    definitions: Res<Assets<PassiveDefinition>>,
+) {
+    for (passives, mut stats) in players.iter_mut() {
+        for item in passives.0.iter() {
+            let Some(definition) = definitions.get(&item.definition) else {
+                continue;
+            };
+            let modifiers = (0..item.level).flat_map(|_| definition.modifiers.iter().copied());
+            stats.set_modifiers(ModifierSource::Passive(item.definition.id()), modifiers);
+        }
+    }
+}
+
+fn setup_passive_hud(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(8.0),
+                left: Val::Px(8.0),
+                column_gap: Val::Px(4.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(PassiveHud)
+        .insert(RunEntity);
+}
+
+/// Rebuilds the row of icons, each labelled with the item's level.
+fn update_passive_hud(
+    mut commands: Commands,
+    players: Query<&PassiveItems, (With<Player>, Changed<PassiveItems>)>,
+    hud: Query<Entity, With<PassiveHud>>,
+    definitions: Res<Assets<PassiveDefinition>>,
+    assets: Res<AssetServer>,
+) {
+    let (Ok(passives), Ok(hud)) = (players.get_single(), hud.get_single()) else {
+        return;
+    };
+    commands.entity(hud).despawn_descendants();
+    commands.entity(hud).with_children(|hud| {
+        for item in passives.0.iter() {
+            let Some(definition) = definitions.get(&item.definition) else {
+                continue;
+            };
+            let style = Style {
+                width: ICON_SIZE,
+                height: ICON_SIZE,
+                justify_content: JustifyContent::End,
+                align_items: AlignItems::End,
+                ..default()
+            };
+            let mut icon = match &definition.icon {
+                Some(icon) => hud.spawn(ImageBundle {
+                    style,
+                    image: UiImage::new(assets.load(icon.clone())),
+                    ..default()
+                }),
+                None => hud.spawn(NodeBundle {
+                    style,
+                    background_color: definition.colour.into(),
+                    ..default()
+                }),
+            };
+            icon.with_children(|icon| {
+                icon.spawn(TextBundle::from_section(
+                    item.level.to_string(),
+                    TextStyle {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                ));
+            });
+        }
+    });
+}
@@ -0,0 +1,152 @@
+use bevy::prelude::*;
+use bevy_ineffable::prelude::*;
+use bevy_rapier2d::plugin::RapierConfiguration;
+
+use crate::{
+    feedback::HurtVignette,
+    input::MenuInput,
+    menu::{spawn_menu, MenuSelected},
+    GameState,
+};
+
+/// Freezes the run behind a menu. Pausing stops virtual time, which halts every timer and
+/// animation, and the physics pipeline.
+pub struct PausePlugin;
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_sub_state::<PlayState>();
+        app.add_systems(Update, toggle_pause.run_if(in_state(GameState::Playing)));
+        app.add_systems(OnEnter(PlayState::Paused), (pause, setup_pause_menu));
+        app.add_systems(OnExit(PlayState::Paused), (resume, teardown_pause_menus));
+        app.add_systems(Update, pause_selected.run_if(in_state(PlayState::Paused)));
+        app.add_systems(
+            Update,
+            settings_selected.run_if(in_state(PlayState::Paused)),
+        );
+    }
+}
+
+#[derive(SubStates, Debug, Default, Hash, Clone, PartialEq, Eq)]
+#[source(GameState = GameState::Playing)]
+pub(crate) enum PlayState {
+    #[default]
+    Running,
+    Paused,
+    /// Choosing an upgrade, which freezes the run just like pausing.
+    LevelUp,
+}
+
+#[derive(Component)]
+struct PauseMenu;
+
+#[derive(Component)]
+struct SettingsMenu;
+
+fn toggle_pause(
+    bindings: Res<Ineffable>,
+    play_state: Res<State<PlayState>>,
+    mut next_state: ResMut<NextState<PlayState>>,
+) {
+    if !bindings.just_pulsed(ineff!(MenuInput::Pause)) {
+        return;
+    }
+    match play_state.get() {
+        PlayState::Running => next_state.set(PlayState::Paused),
+        PlayState::Paused => next_state.set(PlayState::Running),
+        // An upgrade has to be chosen first.
+        PlayState::LevelUp => {}
+    }
+}
+
+pub(crate) fn pause(
+    mut time: ResMut<Time<Virtual>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    time.pause();
+    rapier_config.physics_pipeline_active = false;
+}
+
+pub(crate) fn resume(
+    mut time: ResMut<Time<Virtual>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    time.unpause();
+    rapier_config.physics_pipeline_active = true;
+}
+
+fn setup_pause_menu(mut commands: Commands) {
+    spawn_pause_menu(&mut commands);
+}
+
+fn spawn_pause_menu(commands: &mut Commands) {
+    let menu = spawn_menu(
+        commands,
+        "Paused",
+        &[],
+        &["Resume", "Settings", "Restart", "Quit to start screen"],
+    );
+    commands.entity(menu).insert(PauseMenu);
+}
+
+fn spawn_settings_menu(commands: &mut Commands, vignette: &HurtVignette) {
+    let vignette = format!(
+        "Hurt vignette: {}",
+        if vignette.enabled { "On" } else { "Off" }
+    );
+    let menu = spawn_menu(commands, "Settings", &[], &[&vignette, "Back"]);
+    commands.entity(menu).insert(SettingsMenu);
+}
+
+fn pause_selected(
+    mut commands: Commands,
+    mut selected: EventReader<MenuSelected>,
+    menus: Query<(), With<PauseMenu>>,
+    vignette: Res<HurtVignette>,
+    mut play_state: ResMut<NextState<PlayState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for selected in selected.read() {
+        if !menus.contains(selected.menu) {
+            continue;
+        }
+        match selected.option {
+            0 => play_state.set(PlayState::Running),
+            1 => {
+                commands.entity(selected.menu).despawn_recursive();
+                spawn_settings_menu(&mut commands, &vignette);
+            }
+            2 => game_state.set(GameState::Restarting),
+            _ => game_state.set(GameState::StartScreen),
+        }
+    }
+}
+
+fn settings_selected(
+    mut commands: Commands,
+    mut selected: EventReader<MenuSelected>,
+    menus: Query<(), With<SettingsMenu>>,
+    mut vignette: ResMut<HurtVignette>,
+) {
+    for selected in selected.read() {
+        if !menus.contains(selected.menu) {
+            continue;
+        }
+        commands.entity(selected.menu).despawn_recursive();
+        match selected.option {
+            0 => {
+                vignette.enabled = !vignette.enabled;
+                spawn_settings_menu(&mut commands, &vignette);
+            }
+            _ => spawn_pause_menu(&mut commands),
+        }
+    }
+}
+
+fn teardown_pause_menus(
+    mut commands: Commands,
+    menus: Query<Entity, Or<(With<PauseMenu>, With<SettingsMenu>)>>,
+) {
+    for menu in menus.iter() {
+        commands.entity(menu).despawn_recursive();
+    }
+}
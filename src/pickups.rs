@@ -13,8 +13,14 @@ use bevy_spritesheet_animation::{
     animation::AnimationDuration, component::SpritesheetAnimation, library::SpritesheetLibrary,
     spritesheet::Spritesheet,
 };
+use serde::Deserialize;
 
-use crate::{GameState, Player, PICKUP_GROUP};
+use crate::{
+    damage::{DamageEvent, DamageFlags, DamageType},
+    enemies::{Enemy, Reaper},
+    run::{RunEntity, RunStats},
+    Dead, GameState, Health, Player, PICKUP_GROUP,
+};
 
 pub struct PickupsPlugin;
 
@@ -28,6 +34,7 @@ impl Plugin for PickupsPlugin {
         );
         app.add_systems(Update, attract_pickup.run_if(in_state(GameState::Playing)));
         app.add_systems(Update, pickup_pickup.run_if(in_state(GameState::Playing)));
+        app.add_event::<ChestOpened>();
     }
 }
 
@@ -48,12 +55,55 @@ pub const EXP_ANIMATION: &str = "experience orb";
 
 #[derive(Component)]
 pub struct Pickup {
-    action: fn(&mut Player),
+    /// Run with the player who picked it up.
+    action: fn(&mut World, Entity),
+}
+
+/// Tiers of experience orb, dropped by stronger enemies for more experience.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExperienceGem {
+    Small,
+    Medium,
+    Large,
 }
+
+impl ExperienceGem {
+    fn action(self) -> fn(&mut World, Entity) {
+        match self {
+            ExperienceGem::Small => gain_experience::<100>,
+            ExperienceGem::Medium => gain_experience::<500>,
+            ExperienceGem::Large => gain_experience::<2500>,
+        }
+    }
+
+    fn size(self) -> f32 {
+        match self {
+            ExperienceGem::Small => 16.0,
+            ExperienceGem::Medium => 20.0,
+            ExperienceGem::Large => 26.0,
+        }
+    }
+
+    fn tint(self) -> Color {
+        match self {
+            ExperienceGem::Small => Color::WHITE,
+            ExperienceGem::Medium => Color::srgb(0.5, 1.0, 0.5),
+            ExperienceGem::Large => Color::srgb(1.0, 0.5, 0.5),
+        }
+    }
+}
+
+fn gain_experience<const EXPERIENCE: u64>(world: &mut World, player: Entity) {
+    if let Some(mut player) = world.get_mut::<Player>(player) {
+        player.experience += EXPERIENCE;
+    }
+}
+
 pub fn spawn_experience_pickup(
     library: &Res<SpritesheetLibrary>,
     atlas_layouts: &mut ResMut<Assets<TextureAtlasLayout>>,
     assets: &Res<AssetServer>,
+    gem: ExperienceGem,
     origin: Transform,
 ) -> CommandQueue {
     let mut command_queue = CommandQueue::default();
@@ -77,7 +127,8 @@ pub fn spawn_experience_pickup(
         world
             .spawn(SpriteBundle {
                 sprite: Sprite {
-                    custom_size: Some(Vec2::splat(16.0)),
+                    custom_size: Some(Vec2::splat(gem.size())),
+                    color: gem.tint(),
                     ..default()
                 },
                 texture: texture.clone(),
@@ -89,11 +140,10 @@ pub fn spawn_experience_pickup(
                 ..default()
             })
             .insert(Pickup {
-                action: |player| {
-                    player.experience += 100;
-                },
+                action: gem.action(),
             })
             .insert(SpritesheetAnimation::from_id(animation_id))
+            .insert(RunEntity)
             .insert(Collider::ball(8.0))
             .insert(RigidBody::Dynamic)
             .insert(ActiveEvents::COLLISION_EVENTS)
@@ -147,26 +197,159 @@ fn attract_pickup(
 fn pickup_pickup(
     mut commands: Commands,
     mut collision_events: EventReader<CollisionEvent>,
-    mut player: Query<&mut Player>,
+    player: Query<(), With<Player>>,
     pickups: Query<&Pickup, Without<Player>>,
 ) {
     for collision_event in collision_events.read() {
         if let CollisionEvent::Started(a, b, _flags) = collision_event {
-            if let Ok(mut player) = player.get_mut(*a) {
-                if let Ok(pickup) = pickups.get(*b) {
-                    (pickup.action)(&mut player);
-                    commands.entity(*b).despawn_recursive();
-                }
+            let (player_entity, pickup_entity) = if player.contains(*a) {
+                (*a, *b)
+            } else {
+                (*b, *a)
+            };
+            if !player.contains(player_entity) {
+                continue;
             }
-            if let Ok(mut player) = player.get_mut(*b) {
-                if let Ok(pickup) = pickups.get(*a) {
-                    (pickup.action)(&mut player);
-                    commands.entity(*a).despawn_recursive();
-                }
+            if let Ok(pickup) = pickups.get(pickup_entity) {
+                let action = pickup.action;
+                // Gone before its action runs, so a magnet doesn't draw in itself.
+                commands.entity(pickup_entity).despawn_recursive();
+                commands.add(move |world: &mut World| action(world, player_entity));
             }
         }
     }
 }
+
+#[derive(Event)]
+pub struct ChestOpened {
+    pub player: Entity,
+}
+
+/// Everything other than experience that enemies can drop.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PickupKind {
+    /// Heals the player.
+    Food,
+    /// Draws in every other pickup on screen.
+    Magnet,
+    /// Currency kept between runs.
+    Gold,
+    /// Kills every enemy on screen, other than the reaper.
+    Bomb,
+    /// Evolves or upgrades one of the player's weapons.
+    Chest,
+}
+
+/// Health restored by food.
+const FOOD_HEALING: u32 = 10;
+/// Roughly how far from the player the screen reaches.
+const SCREEN_RADIUS: f32 = 384.0;
+
+impl PickupKind {
+    fn action(self) -> fn(&mut World, Entity) {
+        match self {
+            PickupKind::Food => |world, player| {
+                if let Some(mut health) = world.get_mut::<Health>(player) {
+                    health.current = (health.current + FOOD_HEALING).min(health.max);
+                }
+            },
+            PickupKind::Magnet => |world, player| {
+                let Some(centre) = world
+                    .get::<Transform>(player)
+                    .map(|transform| transform.translation)
+                else {
+                    return;
+                };
+                let pickups: Vec<_> = world
+                    .query_filtered::<(Entity, &Transform), With<Pickup>>()
+                    .iter(world)
+                    .filter(|(_, transform)| {
+                        transform.translation.distance(centre) <= SCREEN_RADIUS
+                    })
+                    .map(|(pickup, _)| pickup)
+                    .collect();
+                for pickup in pickups {
+                    world.entity_mut(pickup).insert(AttractedTo);
+                }
+            },
+            PickupKind::Gold => |world, _| {
+                world.resource_mut::<RunStats>().gold += 1;
+            },
+            PickupKind::Bomb => detonate,
+            PickupKind::Chest => |world, player| {
+                world.send_event(ChestOpened { player });
+            },
+        }
+    }
+
+    fn colour(self) -> Color {
+        match self {
+            PickupKind::Food => Color::srgb(0.85, 0.25, 0.2),
+            PickupKind::Magnet => Color::srgb(0.3, 0.45, 0.9),
+            PickupKind::Gold => Color::srgb(1.0, 0.85, 0.1),
+            PickupKind::Bomb => Color::srgb(0.2, 0.2, 0.2),
+            PickupKind::Chest => Color::srgb(0.85, 0.65, 0.15),
+        }
+    }
+
+    fn size(self) -> f32 {
+        match self {
+            PickupKind::Gold => 8.0,
+            PickupKind::Chest => 20.0,
+            _ => 14.0,
+        }
+    }
+}
+
+/// Deals every enemy near the player as much damage as it has health left.
+fn detonate(world: &mut World, player: Entity) {
+    let Some(centre) = world
+        .get::<Transform>(player)
+        .map(|transform| transform.translation)
+    else {
+        return;
+    };
+    let hits: Vec<_> = world
+        .query_filtered::<(Entity, &Transform, &Health), (With<Enemy>, Without<Reaper>, Without<Dead>)>()
+        .iter(world)
+        .filter(|(_, transform, _)| transform.translation.distance(centre) <= SCREEN_RADIUS)
+        .map(|(enemy, _, health)| DamageEvent {
+            source: player,
+            target: enemy,
+            amount: health.current,
+            damage_type: DamageType::Physical,
+            flags: DamageFlags::default(),
+        })
+        .collect();
+    world.send_event_batch(hits);
+}
+
+pub fn spawn_pickup(commands: &mut Commands, kind: PickupKind, origin: Transform) {
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: kind.colour(),
+                custom_size: Some(Vec2::splat(kind.size())),
+                ..default()
+            },
+            transform: origin,
+            ..default()
+        })
+        .insert(Pickup {
+            action: kind.action(),
+        })
+        .insert(RunEntity)
+        .insert(Collider::ball(kind.size() / 2.0))
+        .insert(RigidBody::Dynamic)
+        .insert(ActiveEvents::COLLISION_EVENTS)
+        .insert(CollisionGroups::new(
+            PICKUP_GROUP,
+            crate::PLAYER_PICKUP_GROUP | crate::PLAYER_GROUP,
+        ))
+        .insert(KinematicCharacterController::default())
+        .insert(Sensor);
+}
+
 #[derive(Component)]
 struct AttractedTo;
 #[derive(Component)]
@@ -1,108 +1,155 @@
+use std::{
+    f32::consts::{PI, TAU},
+    time::Duration,
+};
+
 use bevy::{
     prelude::*,
     render::texture::{ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor},
+    utils::HashMap,
 };
 use bevy_rapier2d::{
-    dynamics::{ExternalImpulse, RigidBody},
+    dynamics::{RigidBody, Velocity},
     geometry::{ActiveEvents, Collider, CollisionGroups, Sensor},
-    pipeline::CollisionEvent,
+    pipeline::{CollisionEvent, QueryFilter},
+    plugin::RapierContext,
 };
 use bevy_spritesheet_animation::{
-    component::SpritesheetAnimation, library::SpritesheetLibrary, spritesheet::Spritesheet,
+    animation::AnimationDuration, component::SpritesheetAnimation, library::SpritesheetLibrary,
+    spritesheet::Spritesheet,
 };
+use serde::Deserialize;
 
-use crate::{DamageBuffer, DamageSource, GameState, Health, Hurt, Player};
+use crate::{
+    damage::{Damage, DamageBuffer, DamageEvent, DamageRoll, DamageSource, DamageType},
+    enemies::Enemy,
+    generation::Terrain,
+    run::RunEntity,
+    weapons::{ProjectileSprite, WeaponDefinition, WeaponKind},
+    Dead, GameState, Player, PROJECTILE_GROUP, TERRAIN_GROUP,
+};
 
 pub struct ProjectilesPlugin;
 impl Plugin for ProjectilesPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
             Update,
-            spawn_pure_projectile.run_if(in_state(GameState::Playing)),
+            remove_projectile.run_if(in_state(GameState::Playing)),
         );
         app.add_systems(
             Update,
-            remove_projectile.run_if(in_state(GameState::Playing)),
+            projectile_collide.run_if(in_state(GameState::Playing)),
         );
         app.add_systems(
             Update,
-            projectile_collide.run_if(in_state(GameState::Playing)),
+            home_projectiles.run_if(in_state(GameState::Playing)),
         );
     }
 }
 
-fn spawn_pure_projectile(
-    mut commands: Commands,
-    mut player: Query<(&Player, &mut PureProjectileSkill, &Transform)>,
-    time: Res<Time>,
-    assets: Res<AssetServer>,
-    mut library: ResMut<SpritesheetLibrary>,
-    mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
-) {
-    for (player, mut skill, transform) in player.iter_mut() {
-        skill.cooldown.tick(time.delta());
-        if skill.cooldown.finished() {
-            let texture = assets.load_with_settings(
-                "projectiles/pure/spritesheet.png",
-                |s: &mut ImageLoaderSettings| match &mut s.sampler {
-                    ImageSampler::Default => s.sampler = ImageSampler::nearest(),
-                    ImageSampler::Descriptor(sampler) => {
-                        *sampler = ImageSamplerDescriptor::nearest();
-                    }
-                },
-            );
-            let layout = atlas_layouts.add(TextureAtlasLayout::from_grid(
-                UVec2::new(200, 200),
-                5,
-                5,
-                None,
-                None,
-            ));
-            let sheet = Spritesheet::new(5, 5);
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_projectile(
+    commands: &mut Commands,
+    assets: &Res<AssetServer>,
+    library: &mut ResMut<SpritesheetLibrary>,
+    atlas_layouts: &mut ResMut<Assets<TextureAtlasLayout>>,
+    weapon: &WeaponDefinition,
+    level: u32,
+    sprite: &ProjectileSprite,
+    transform: Transform,
+) -> Entity {
+    let texture = assets.load_with_settings(
+        sprite.texture.clone(),
+        |s: &mut ImageLoaderSettings| match &mut s.sampler {
+            ImageSampler::Default => s.sampler = ImageSampler::nearest(),
+            ImageSampler::Descriptor(sampler) => {
+                *sampler = ImageSamplerDescriptor::nearest();
+            }
+        },
+    );
+    let layout = atlas_layouts.add(TextureAtlasLayout::from_grid(
+        sprite.tile_size,
+        sprite.columns,
+        sprite.rows,
+        None,
+        None,
+    ));
+    let animation = match library.animation_with_name(&weapon.name) {
+        Some(animation) => animation,
+        None => {
+            let sheet = Spritesheet::new(sprite.columns as usize, sprite.rows as usize);
             let clip = library.new_clip(|clip| {
-                clip.push_frame_indices(sheet.row_partial(0, 0..5));
-                clip.set_default_duration(bevy_spritesheet_animation::animation::AnimationDuration::PerCycle(5000));
+                clip.push_frame_indices(sheet.row_partial(sprite.row, 0..sprite.frames));
+                clip.set_default_duration(AnimationDuration::PerCycle(sprite.duration));
             });
             let animation = library.new_animation(|animation| {
                 animation.add_stage(clip.into());
             });
-            let rotation =
-                Quat::from_axis_angle(Vec3::Z, ((player.facing + 270.0) % 360.0).to_radians());
-            commands
-                .spawn(Projectile {
-                    single: true,
-                    lifespan: Timer::from_seconds(5.0, TimerMode::Once),
-                    damage: 1,
-                })
-                .insert(SpriteBundle {
-                    texture,
-                    sprite: Sprite {
-                        custom_size: Some(Vec2::splat(32.0)),
-                        ..default()
-                    },
-                    transform: Transform::from_translation(transform.translation + Vec3::Z)
-                        .with_rotation(rotation),
-                    ..default()
-                })
-                .insert(TextureAtlas {
-                    layout,
-                    ..default()
-                })
-                .insert(Collider::cuboid(16.0, 8.0))
-                .insert(Sensor)
+            library
+                .name_animation(animation, weapon.name.clone())
+                .unwrap();
+            animation
+        }
+    };
+    let area = weapon.area_at(level);
+    let collider = sprite.collider * area;
+    let mut projectile = commands.spawn(Projectile {
+        lifespan: match weapon.kind {
+            WeaponKind::Aura | WeaponKind::Orbit { .. } => None,
+            _ => Some(Timer::from_seconds(weapon.lifespan, TimerMode::Once)),
+        },
+        damage: weapon.damage_at(level),
+        damage_type: weapon.damage_type,
+        roll: weapon.damage_roll,
+        speed: weapon.speed,
+        on_hit: weapon.on_hit,
+        ricochet: weapon.ricochet,
+        hit_cooldown: weapon.hit_cooldown.map(Duration::from_secs_f32),
+        hits: HashMap::new(),
+    });
+    projectile
+        .insert(RunEntity)
+        .insert(SpriteBundle {
+            texture,
+            sprite: Sprite {
+                custom_size: Some(sprite.size * area),
+                ..default()
+            },
+            transform,
+            ..default()
+        })
+        .insert(TextureAtlas {
+            layout,
+            ..default()
+        })
+        .insert(Sensor)
+        .insert(ActiveEvents::COLLISION_EVENTS)
+        .insert(SpritesheetAnimation::from_id(animation));
+    match weapon.kind {
+        WeaponKind::Projectile | WeaponKind::Chain { .. } | WeaponKind::Beam { .. } => {
+            projectile
+                .insert(Collider::cuboid(collider.x, collider.y))
                 .insert(CollisionGroups::new(
-                    crate::PROJECTILE_GROUP,
-                    crate::ENEMY_GROUP,
+                    PROJECTILE_GROUP,
+                    crate::ENEMY_GROUP | TERRAIN_GROUP,
                 ))
-                .insert(ActiveEvents::COLLISION_EVENTS)
                 .insert(RigidBody::Dynamic)
-                .insert(ExternalImpulse {
-                    impulse: (rotation * (Vec3::X * (1024.0 * 64.0))).truncate(),
-                    torque_impulse: 0.0,
-                })
-                .insert(SpritesheetAnimation::from_id(animation));
+                .insert(Velocity::linear(
+                    (transform.rotation * (Vec3::X * weapon.speed)).truncate(),
+                ));
+        }
+        WeaponKind::Orbit { .. } => {
+            projectile
+                .insert(Collider::cuboid(collider.x, collider.y))
+                .insert(CollisionGroups::new(PROJECTILE_GROUP, crate::ENEMY_GROUP));
+        }
+        WeaponKind::Aura | WeaponKind::Zone => {
+            projectile
+                .insert(Collider::ball(collider.x))
+                .insert(CollisionGroups::new(PROJECTILE_GROUP, crate::ENEMY_GROUP));
         }
     }
+    projectile.id()
 }
 
 fn remove_projectile(
@@ -111,74 +158,195 @@ fn remove_projectile(
     time: ResMut<Time>,
 ) {
     for (entity, mut projectile) in projectiles.iter_mut() {
-        projectile.lifespan.tick(time.delta());
-        if projectile.lifespan.just_finished() {
+        let Some(lifespan) = &mut projectile.lifespan else {
+            continue;
+        };
+        lifespan.tick(time.delta());
+        if lifespan.just_finished() {
             commands.entity(entity).despawn_recursive();
         }
     }
 }
 
+fn home_projectiles(
+    mut projectiles: Query<(&mut Homing, &Projectile, &mut Velocity, &mut Transform)>,
+    enemies: Query<(Entity, &Transform), (With<Enemy>, Without<Dead>, Without<Projectile>)>,
+    time: Res<Time>,
+) {
+    for (mut homing, projectile, mut velocity, mut transform) in projectiles.iter_mut() {
+        let origin = transform.translation;
+        let target = match homing.target.and_then(|target| enemies.get(target).ok()) {
+            Some((_, target)) => target.translation,
+            None => {
+                let Some((entity, target)) = enemies
+                    .iter()
+                    .filter(|(enemy, _)| !projectile.hits.contains_key(enemy))
+                    .min_by(|(_, a), (_, b)| {
+                        origin
+                            .distance(a.translation)
+                            .total_cmp(&origin.distance(b.translation))
+                    })
+                else {
+                    continue;
+                };
+                homing.target = Some(entity);
+                target.translation
+            }
+        };
+        let current = velocity.linvel.to_angle();
+        let desired = (target - origin).truncate().to_angle();
+        let mut turn = desired - current;
+        if turn > PI {
+            turn -= TAU;
+        } else if turn < -PI {
+            turn += TAU;
+        }
+        let max_turn = homing.turn_rate.to_radians() * time.delta_seconds();
+        let heading = current + turn.clamp(-max_turn, max_turn);
+        aim(
+            &mut velocity,
+            &mut transform,
+            Vec2::from_angle(heading),
+            projectile.speed,
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn projectile_collide(
     mut commands: Commands,
     mut collision_events: EventReader<CollisionEvent>,
-    projectile: Query<(&Projectile, Option<&Children>)>,
-    damage_source: Query<Entity, With<DamageSource>>,
-    mut other: Query<(&mut DamageBuffer, &mut Health)>,
+    mut projectile: Query<(
+        &mut Projectile,
+        Option<&mut Velocity>,
+        &mut Transform,
+        Option<&Children>,
+    )>,
+    damage_source: Query<&DamageSource>,
+    mut other: Query<&mut DamageBuffer>,
+    enemies: Query<(Entity, &Transform), (With<Enemy>, Without<Dead>, Without<Projectile>)>,
+    terrain: Query<(), With<Terrain>>,
+    player: Query<&DamageRoll, With<Player>>,
+    mut damage_events: EventWriter<DamageEvent>,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>,
 ) {
     for collision_event in collision_events.read() {
         match collision_event {
             CollisionEvent::Started(a, b, _flags) => {
-                if let Ok((projectile, _)) = projectile.get(*a) {
-                    if let Ok((mut other, mut health)) = other.get_mut(*b) {
-                        if projectile.single {
-                            health.current = health.current.saturating_sub(projectile.damage);
-                            commands.entity(*b).try_insert(Hurt{timer:Timer::from_seconds(0.5, TimerMode::Once)});
-                            commands.entity(*a).despawn_recursive();
-                        } else {
-                            let damage_entity = commands.spawn(DamageSource).id();
-                            commands.entity(*a).add_child(damage_entity);
-                            other.0.push(crate::Damage {
-                                source: damage_entity,
-                                amount: projectile.damage,
-                            });
-                        }
+                let (projectile_entity, other_entity) = if projectile.contains(*a) {
+                    (*a, *b)
+                } else if projectile.contains(*b) {
+                    (*b, *a)
+                } else {
+                    continue;
+                };
+                let (mut projectile, velocity, mut transform, _) =
+                    projectile.get_mut(projectile_entity).unwrap();
+                if terrain.contains(other_entity) {
+                    let Some(mut velocity) = velocity else {
+                        continue;
+                    };
+                    if projectile.ricochet == 0 {
+                        commands.entity(projectile_entity).despawn_recursive();
+                        continue;
                     }
-                } else if let Ok((projectile, _)) = projectile.get(*b) {
-                    if let Ok((mut other, mut health)) = other.get_mut(*a) {
-                        if projectile.single {
-                            health.current = health.current.saturating_sub(projectile.damage);
-                            commands.entity(*a).try_insert(Hurt{timer:Timer::from_seconds(0.5, TimerMode::Once)});
-                            commands.entity(*b).despawn_recursive();
-                        } else {
-                            let damage_entity = commands.spawn(DamageSource).id();
-                            commands.entity(*b).add_child(damage_entity);
-                            other.0.push(crate::Damage {
-                                source: damage_entity,
-                                amount: projectile.damage,
-                            });
-                        }
+                    projectile.ricochet -= 1;
+                    let direction = velocity.linvel.normalize_or_zero();
+                    let origin = transform.translation.truncate() - direction * RICOCHET_PROBE;
+                    if let Some((_, hit)) = rapier_context.cast_ray_and_get_normal(
+                        origin,
+                        direction,
+                        RICOCHET_PROBE * 2.0,
+                        false,
+                        QueryFilter::new()
+                            .groups(CollisionGroups::new(PROJECTILE_GROUP, TERRAIN_GROUP)),
+                    ) {
+                        let reflected = direction - 2.0 * direction.dot(hit.normal) * hit.normal;
+                        aim(&mut velocity, &mut transform, reflected, projectile.speed);
                     }
+                    continue;
                 }
-            }
-            CollisionEvent::Stopped(a, b, _flags) => {
-                if let Ok((_, children)) = projectile.get(*a) {
-                    if other.get(*b).is_ok() {
-                        if let Some(children) = children {
-                            for &child in children.iter() {
-                                if let Ok(source) = damage_source.get(child) {
-                                    commands.entity(source).despawn_recursive();
-                                }
+                let Ok(mut other) = other.get_mut(other_entity) else {
+                    continue;
+                };
+                let roll = player
+                    .get_single()
+                    .map_or(projectile.roll, |player| player.combine(projectile.roll));
+                if let OnHit::Linger = projectile.on_hit {
+                    let damage_entity = commands
+                        .spawn(DamageSource {
+                            target: other_entity,
+                        })
+                        .id();
+                    commands.entity(projectile_entity).add_child(damage_entity);
+                    other.0.push(Damage::new(
+                        damage_entity,
+                        projectile.damage,
+                        projectile.damage_type,
+                        projectile.hit_cooldown.unwrap_or_default(),
+                        roll,
+                    ));
+                    continue;
+                }
+                let now = time.elapsed();
+                if let Some(last_hit) = projectile.hits.get(&other_entity) {
+                    match projectile.hit_cooldown {
+                        Some(cooldown) if now - *last_hit >= cooldown => {}
+                        _ => continue,
+                    }
+                }
+                projectile.hits.insert(other_entity, now);
+                damage_events.send(roll.event(
+                    projectile_entity,
+                    other_entity,
+                    projectile.damage,
+                    projectile.damage_type,
+                    true,
+                ));
+                match projectile.on_hit {
+                    OnHit::Pierce(ref mut remaining) if *remaining > 0 => *remaining -= 1,
+                    OnHit::Bounce(ref mut remaining) if *remaining > 0 => {
+                        *remaining -= 1;
+                        let origin = transform.translation;
+                        let next = enemies
+                            .iter()
+                            .filter(|(enemy, _)| !projectile.hits.contains_key(enemy))
+                            .map(|(_, enemy)| enemy.translation)
+                            .filter(|enemy| origin.distance(*enemy) <= BOUNCE_RANGE)
+                            .min_by(|a, b| origin.distance(*a).total_cmp(&origin.distance(*b)));
+                        match (next, velocity) {
+                            (Some(next), Some(mut velocity)) => {
+                                let speed = projectile.speed;
+                                aim(
+                                    &mut velocity,
+                                    &mut transform,
+                                    (next - origin).truncate(),
+                                    speed,
+                                );
                             }
+                            (None, _) => commands.entity(projectile_entity).despawn_recursive(),
+                            _ => {}
                         }
                     }
-                } else if let Ok((_, children)) = projectile.get(*b) {
-                    if other.get(*a).is_ok() {
-                        if let Some(children) = children {
-                            for &child in children.iter() {
-                                if let Ok(source) = damage_source.get(child) {
-                                    commands.entity(source).despawn_recursive();
-                                }
-                            }
+                    _ => commands.entity(projectile_entity).despawn_recursive(),
+                }
+            }
+            CollisionEvent::Stopped(a, b, _flags) => {
+                let (projectile_entity, other_entity) = if projectile.contains(*a) {
+                    (*a, *b)
+                } else if projectile.contains(*b) {
+                    (*b, *a)
+                } else {
+                    continue;
+                };
+                let Ok((_, _, _, Some(children))) = projectile.get(projectile_entity) else {
+                    continue;
+                };
+                for &child in children.iter() {
+                    if let Ok(source) = damage_source.get(child) {
+                        if source.target == other_entity {
+                            commands.entity(child).despawn_recursive();
                         }
                     }
                 }
@@ -187,13 +355,49 @@ fn projectile_collide(
     }
 }
 
+/// Turns a projectile to travel along `direction`, keeping its sprite pointed the same way.
+fn aim(velocity: &mut Velocity, transform: &mut Transform, direction: Vec2, speed: f32) {
+    let direction = direction.normalize_or_zero();
+    velocity.linvel = direction * speed;
+    transform.rotation = Quat::from_rotation_z(direction.to_angle());
+}
+
+/// Steers a projectile toward an enemy, picking the nearest one whenever it has none.
+#[derive(Component)]
+pub struct Homing {
+    pub target: Option<Entity>,
+    /// Degrees per second the projectile can turn.
+    pub turn_rate: f32,
+}
+
+/// How far a bouncing projectile will look for its next target.
+const BOUNCE_RANGE: f32 = 256.0;
+/// Distance either side of a projectile that is searched for the terrain edge it hit.
+const RICOCHET_PROBE: f32 = 32.0;
+
 #[derive(Component)]
 struct Projectile {
-    lifespan: Timer,
+    /// Weapons that stay with the player, like auras, have no lifespan.
+    lifespan: Option<Timer>,
     damage: u32,
-    single: bool,
+    damage_type: DamageType,
+    /// The weapon's crit and variance bonuses.
+    roll: DamageRoll,
+    speed: f32,
+    on_hit: OnHit,
+    ricochet: u32,
+    hit_cooldown: Option<Duration>,
+    /// When each target was last hit, so a shot never hits the same enemy twice.
+    hits: HashMap<Entity, Duration>,
 }
-#[derive(Component)]
-pub struct PureProjectileSkill {
-    pub(crate) cooldown: Timer,
+
+/// What a projectile does after it damages an enemy.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum OnHit {
+    /// Passes through this many more enemies before despawning.
+    Pierce(u32),
+    /// Redirects to the nearest enemy it has not hit yet, this many times.
+    Bounce(u32),
+    /// Stays alive and keeps damaging whatever it overlaps.
+    Linger,
 }
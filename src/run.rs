@@ -0,0 +1,351 @@
+use bevy::prelude::*;
+use bevy_rapier2d::plugin::RapierConfiguration;
+use bevy_spritesheet_animation::component::SpritesheetAnimation;
+use rand::{thread_rng, Rng};
+
+use crate::{
+    animations,
+    damage::{DamageDealt, EntityKilled},
+    enemies::Reaper,
+    feedback::{CastAnimation, HurtAnimation},
+    menu::{spawn_menu, MenuSelected},
+    meta::{MetaProgress, COMPLETION_UNLOCK},
+    GameState, Level, Player, StartScreen,
+};
+
+/// Everything that spans a single run: its seed, its stats, and how it ends.
+pub struct RunPlugin;
+impl Plugin for RunPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunStats>();
+        app.init_resource::<RunMode>();
+        app.add_systems(OnEnter(GameState::StartScreen), setup_mode_menu);
+        app.add_systems(
+            Update,
+            mode_selected.run_if(in_state(GameState::StartScreen)),
+        );
+        app.add_systems(OnExit(GameState::StartScreen), roll_seed);
+        app.add_systems(
+            OnEnter(GameState::Playing),
+            (reset_run_stats, resume_physics),
+        );
+        app.add_systems(Update, track_run_stats.run_if(in_state(GameState::Playing)));
+        app.add_systems(Update, player_death.run_if(in_state(GameState::Playing)));
+        app.add_systems(Update, reaper_defeated.run_if(in_state(GameState::Playing)));
+        app.add_systems(OnEnter(GameState::Dying), start_dying);
+        app.add_systems(Update, dying.run_if(in_state(GameState::Dying)));
+        app.add_systems(
+            OnEnter(GameState::DeathScreen),
+            (bank_gold, record_endless_time, setup_death_screen).chain(),
+        );
+        app.add_systems(
+            OnEnter(GameState::Victory),
+            (record_completion, bank_gold, setup_victory_screen).chain(),
+        );
+        app.add_systems(
+            Update,
+            results_selected
+                .run_if(in_state(GameState::DeathScreen).or_else(in_state(GameState::Victory))),
+        );
+        app.add_systems(
+            OnExit(GameState::DeathScreen),
+            (teardown_results, cleanup_run),
+        );
+        app.add_systems(OnExit(GameState::Victory), (teardown_results, cleanup_run));
+        // Runs abandoned from the pause menu.
+        app.add_systems(OnExit(GameState::Restarting), cleanup_run);
+        app.add_systems(Update, restart.run_if(in_state(GameState::Restarting)));
+        app.add_systems(
+            OnTransition {
+                exited: GameState::Playing,
+                entered: GameState::StartScreen,
+            },
+            cleanup_run,
+        );
+    }
+}
+
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RunMode {
+    /// Survive until the level's runtime is up, then defeat the reaper.
+    #[default]
+    Timed,
+    /// The runtime never ends, and the run only ends in death.
+    Endless,
+}
+
+/// Seeds the terrain, enemy spawns and drops, so a retried run plays on the same map.
+#[derive(Resource)]
+pub struct RunSeed(pub u32);
+
+#[derive(Resource, Default)]
+pub struct RunStats {
+    pub kills: u32,
+    pub damage_dealt: u64,
+    /// Added to the meta progress once the run is over.
+    pub gold: u64,
+}
+
+/// Despawned, with its children, when the run is over.
+#[derive(Component)]
+pub struct RunEntity;
+
+#[derive(Resource)]
+struct DeathTimer(Timer);
+
+/// The start screen's choice of [`RunMode`].
+#[derive(Component)]
+struct ModeMenu;
+
+/// Where the last endless run placed on the leaderboard.
+#[derive(Resource)]
+struct LeaderboardPlace(Option<usize>);
+
+/// How many leaderboard entries the start screen lists.
+const LEADERBOARD_SHOWN: usize = 5;
+
+fn format_time(seconds: u64) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn setup_mode_menu(mut commands: Commands, meta: Res<MetaProgress>) {
+    let lines: Vec<_> = meta
+        .endless_times
+        .iter()
+        .take(LEADERBOARD_SHOWN)
+        .enumerate()
+        .map(|(place, &time)| format!("{}. {}", place + 1, format_time(time)))
+        .collect();
+    let menu = spawn_menu(
+        &mut commands,
+        "Endless best times",
+        &lines,
+        &["Survive 15:00", "Endless"],
+    );
+    // Torn down along with the rest of the start screen.
+    commands.entity(menu).insert((ModeMenu, StartScreen));
+}
+
+fn mode_selected(
+    mut selected: EventReader<MenuSelected>,
+    menus: Query<(), With<ModeMenu>>,
+    mut run_mode: ResMut<RunMode>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for selected in selected.read() {
+        if !menus.contains(selected.menu) {
+            continue;
+        }
+        *run_mode = match selected.option {
+            0 => RunMode::Timed,
+            _ => RunMode::Endless,
+        };
+        next_state.set(GameState::CharacterSelect);
+    }
+}
+
+/// The menu shown at the end of a run, won or lost.
+#[derive(Component)]
+struct ResultsMenu;
+
+fn roll_seed(mut commands: Commands) {
+    commands.insert_resource(RunSeed(thread_rng().gen()));
+}
+
+fn reset_run_stats(mut stats: ResMut<RunStats>) {
+    *stats = RunStats::default();
+}
+
+fn resume_physics(mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.physics_pipeline_active = true;
+}
+
+fn track_run_stats(
+    mut stats: ResMut<RunStats>,
+    mut damage_dealt: EventReader<DamageDealt>,
+    mut killed: EventReader<EntityKilled>,
+    player: Query<(), With<Player>>,
+) {
+    for hit in damage_dealt.read() {
+        if !player.contains(hit.target) {
+            stats.damage_dealt += hit.amount as u64;
+        }
+    }
+    for killed in killed.read() {
+        if !player.contains(killed.entity) {
+            stats.kills += 1;
+        }
+    }
+}
+
+fn player_death(
+    mut killed: EventReader<EntityKilled>,
+    player: Query<(), With<Player>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if killed.read().any(|killed| player.contains(killed.entity)) {
+        next_state.set(GameState::Dying);
+    }
+}
+
+/// Freezes the world while the player's death animation plays.
+fn start_dying(
+    mut commands: Commands,
+    player: Query<(Entity, &Player)>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    rapier_config.physics_pipeline_active = false;
+    if let Ok((entity, player)) = player.get_single() {
+        let mut entity = commands.entity(entity);
+        entity.remove::<(HurtAnimation, CastAnimation)>();
+        if let Some(animation) = player.animations.get(animations::DEATH, player.direction()) {
+            entity.insert(SpritesheetAnimation::from_id(animation));
+        }
+    }
+    commands.insert_resource(DeathTimer(Timer::from_seconds(2.0, TimerMode::Once)));
+}
+
+fn dying(
+    mut timer: ResMut<DeathTimer>,
+    time: Res<Time>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    timer.0.tick(time.delta());
+    if timer.0.finished() {
+        next_state.set(GameState::DeathScreen);
+    }
+}
+
+/// Ends the run in victory once the reaper falls.
+fn reaper_defeated(
+    mut killed: EventReader<EntityKilled>,
+    reapers: Query<(), With<Reaper>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if killed.read().any(|killed| reapers.contains(killed.entity)) {
+        next_state.set(GameState::Victory);
+    }
+}
+
+fn run_summary(stats: &RunStats, level: &Level, player: Option<&Player>) -> Vec<String> {
+    vec![
+        format!(
+            "Survived {}",
+            format_time(level.runtime.elapsed().as_secs())
+        ),
+        format!("Level {}", player.map_or(1, |player| player.level)),
+        format!("Kills {}", stats.kills),
+        format!("Damage dealt {}", stats.damage_dealt),
+        format!("Gold {}", stats.gold),
+    ]
+}
+
+/// Keeps the gold picked up over a run, however it ended.
+fn bank_gold(stats: Res<RunStats>, mut meta: ResMut<MetaProgress>) {
+    meta.gold += stats.gold;
+    meta.save();
+}
+
+/// Endless runs only end in death, so this is where they make the leaderboard.
+fn record_endless_time(
+    mut commands: Commands,
+    run_mode: Res<RunMode>,
+    level: Res<Level>,
+    mut meta: ResMut<MetaProgress>,
+) {
+    if *run_mode != RunMode::Endless {
+        commands.insert_resource(LeaderboardPlace(None));
+        return;
+    }
+    let place = meta.record_endless_time(level.runtime.elapsed().as_secs());
+    meta.save();
+    commands.insert_resource(LeaderboardPlace(place));
+}
+
+fn setup_death_screen(
+    mut commands: Commands,
+    stats: Res<RunStats>,
+    level: Res<Level>,
+    player: Query<&Player>,
+    place: Res<LeaderboardPlace>,
+) {
+    let mut lines = run_summary(&stats, &level, player.get_single().ok());
+    if let Some(place) = place.0 {
+        lines.push(format!("Leaderboard #{}", place + 1));
+    }
+    let menu = spawn_menu(
+        &mut commands,
+        "You Died",
+        &lines,
+        &["Retry", "Return to start"],
+    );
+    commands.entity(menu).insert(ResultsMenu);
+}
+
+fn record_completion(
+    mut meta: ResMut<MetaProgress>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    rapier_config.physics_pipeline_active = false;
+    meta.runs_completed += 1;
+    meta.unlocked.insert(COMPLETION_UNLOCK.to_string());
+    meta.save();
+}
+
+fn setup_victory_screen(
+    mut commands: Commands,
+    stats: Res<RunStats>,
+    level: Res<Level>,
+    player: Query<&Player>,
+    meta: Res<MetaProgress>,
+) {
+    let mut lines = run_summary(&stats, &level, player.get_single().ok());
+    lines.push(format!("Runs completed {}", meta.runs_completed));
+    let menu = spawn_menu(
+        &mut commands,
+        "Victory",
+        &lines,
+        &["Play again", "Return to start"],
+    );
+    commands.entity(menu).insert(ResultsMenu);
+}
+
+fn results_selected(
+    mut selected: EventReader<MenuSelected>,
+    menus: Query<(), With<ResultsMenu>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for selected in selected.read() {
+        if !menus.contains(selected.menu) {
+            continue;
+        }
+        match selected.option {
+            0 => next_state.set(GameState::Playing),
+            _ => next_state.set(GameState::StartScreen),
+        }
+    }
+}
+
+fn teardown_results(mut commands: Commands, menus: Query<Entity, With<ResultsMenu>>) {
+    for menu in menus.iter() {
+        commands.entity(menu).despawn_recursive();
+    }
+}
+
+fn restart(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::Playing);
+}
+
+fn cleanup_run(
+    mut commands: Commands,
+    camera: Query<Entity, With<Camera>>,
+    entities: Query<Entity, With<RunEntity>>,
+) {
+    // The camera follows the player as its child, and outlives the run.
+    for camera in camera.iter() {
+        commands.entity(camera).remove_parent();
+    }
+    for entity in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,285 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_rapier2d::geometry::Collider;
+use serde::Deserialize;
+
+use crate::{
+    damage::{Armour, DamageDealt, DamageType},
+    passives::PassiveDefinition,
+    pause::PlayState,
+    pickups::PlayerPickup,
+    Health,
+};
+
+/// Player stats built from base values and stacked modifiers, applied to the components
+/// they drive whenever they change.
+pub struct StatsPlugin;
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, apply_stats.run_if(in_state(PlayState::Running)));
+        app.add_systems(Update, regenerate.run_if(in_state(PlayState::Running)));
+        app.add_systems(
+            Update,
+            (inflict_status_effects, expire_status_effects).run_if(in_state(PlayState::Running)),
+        );
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stat {
+    /// Pixels per second.
+    MoveSpeed,
+    MaxHealth,
+    /// Health regained per second.
+    Regen,
+    Armour,
+    /// Radius in pixels within which pickups are drawn in.
+    PickupRadius,
+    /// Fraction taken off every weapon cooldown.
+    CooldownReduction,
+    /// Extra projectiles in every volley.
+    ProjectileCount,
+    /// Multiplier on the size of everything the player's weapons spawn.
+    Area,
+    /// Multiplier on the odds of anything lucky happening.
+    Luck,
+}
+
+impl Stat {
+    const ALL: [Stat; 9] = [
+        Stat::MoveSpeed,
+        Stat::MaxHealth,
+        Stat::Regen,
+        Stat::Armour,
+        Stat::PickupRadius,
+        Stat::CooldownReduction,
+        Stat::ProjectileCount,
+        Stat::Area,
+        Stat::Luck,
+    ];
+
+    /// Used for any stat a character doesn't set.
+    fn default_base(self) -> f32 {
+        match self {
+            Stat::MoveSpeed => 72.0,
+            Stat::MaxHealth => 20.0,
+            Stat::PickupRadius => 48.0,
+            Stat::Area | Stat::Luck => 1.0,
+            Stat::Regen | Stat::Armour | Stat::CooldownReduction | Stat::ProjectileCount => 0.0,
+        }
+    }
+}
+
+/// Every `Add` is summed onto the base value before every `Multiply` is applied.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum ModifierOp {
+    Add(f32),
+    Multiply(f32),
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct StatModifier {
+    pub stat: Stat,
+    pub op: ModifierOp,
+}
+
+/// Where a modifier came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ModifierSource {
+    LevelUp,
+    Passive(AssetId<PassiveDefinition>),
+    /// Removed again when the effect wears off.
+    StatusEffect(StatusEffect),
+}
+
+/// Short-lived conditions that modify stats while they last.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StatusEffect {
+    /// Slowed by ice damage.
+    Chilled,
+}
+
+impl StatusEffect {
+    /// The effect taking a hit of `damage_type` inflicts, if any.
+    fn inflicted_by(damage_type: DamageType) -> Option<Self> {
+        match damage_type {
+            DamageType::Ice => Some(StatusEffect::Chilled),
+            _ => None,
+        }
+    }
+
+    pub fn modifiers(self) -> Vec<StatModifier> {
+        match self {
+            StatusEffect::Chilled => vec![StatModifier {
+                stat: Stat::MoveSpeed,
+                op: ModifierOp::Multiply(0.7),
+            }],
+        }
+    }
+
+    /// Seconds the effect lasts, restarted by each new hit.
+    fn duration(self) -> f32 {
+        match self {
+            StatusEffect::Chilled => 2.0,
+        }
+    }
+}
+
+/// The status effects an entity is under, each with the time it has left.
+#[derive(Component, Default, Debug)]
+pub struct StatusEffects(HashMap<StatusEffect, Timer>);
+
+#[derive(Component, Debug)]
+pub struct Stats {
+    base: HashMap<Stat, f32>,
+    modifiers: Vec<(ModifierSource, StatModifier)>,
+    /// Derived from `base` and `modifiers` whenever either changes.
+    values: HashMap<Stat, f32>,
+    /// Health regenerated but not yet a whole point.
+    regen_progress: f32,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new(HashMap::new())
+    }
+}
+
+impl Stats {
+    pub fn new(base: HashMap<Stat, f32>) -> Self {
+        let mut stats = Self {
+            base,
+            modifiers: Vec::new(),
+            values: HashMap::new(),
+            regen_progress: 0.0,
+        };
+        stats.recompute();
+        stats
+    }
+
+    pub fn get(&self, stat: Stat) -> f32 {
+        self.values[&stat]
+    }
+
+    pub fn add_modifier(&mut self, source: ModifierSource, modifier: StatModifier) {
+        self.modifiers.push((source, modifier));
+        self.recompute();
+    }
+
+    pub fn remove_modifiers(&mut self, source: &ModifierSource) {
+        self.modifiers.retain(|(from, _)| from != source);
+        self.recompute();
+    }
+
+    /// Replaces every modifier from `source`.
+    pub fn set_modifiers(
+        &mut self,
+        source: ModifierSource,
+        modifiers: impl IntoIterator<Item = StatModifier>,
+    ) {
+        self.modifiers.retain(|(from, _)| *from != source);
+        self.modifiers.extend(
+            modifiers
+                .into_iter()
+                .map(|modifier| (source.clone(), modifier)),
+        );
+        self.recompute();
+    }
+
+    fn recompute(&mut self) {
+        for stat in Stat::ALL {
+            let base = self
+                .base
+                .get(&stat)
+                .copied()
+                .unwrap_or_else(|| stat.default_base());
+            let modifiers = self
+                .modifiers
+                .iter()
+                .map(|(_, modifier)| modifier)
+                .filter(|modifier| modifier.stat == stat);
+            let (mut added, mut multiplier) = (0.0, 1.0);
+            for modifier in modifiers {
+                match modifier.op {
+                    ModifierOp::Add(amount) => added += amount,
+                    ModifierOp::Multiply(amount) => multiplier *= amount,
+                }
+            }
+            self.values.insert(stat, (base + added) * multiplier);
+        }
+    }
+}
+
+/// Pushes derived stats onto health, armour and the pickup radius.
+fn apply_stats(
+    mut stats: Query<(Entity, &Stats, &mut Health, &mut Armour), Changed<Stats>>,
+    mut pickup_radius: Query<(&Parent, &mut Collider), With<PlayerPickup>>,
+) {
+    for (entity, stats, mut health, mut armour) in stats.iter_mut() {
+        let max = stats.get(Stat::MaxHealth).round().max(1.0) as u32;
+        // Raising max health heals by as much, lowering it only caps current health.
+        if max > health.max {
+            health.current += max - health.max;
+        }
+        health.max = max;
+        health.current = health.current.min(max);
+        armour.0 = stats.get(Stat::Armour).round().max(0.0) as u32;
+        for (parent, mut collider) in pickup_radius.iter_mut() {
+            if parent.get() == entity {
+                *collider = Collider::ball(stats.get(Stat::PickupRadius));
+            }
+        }
+    }
+}
+
+fn regenerate(mut stats: Query<(&mut Stats, &mut Health)>, time: Res<Time>) {
+    for (mut stats, mut health) in stats.iter_mut() {
+        // Ticking along shouldn't count as the stats changing.
+        let stats = stats.bypass_change_detection();
+        if health.current == 0 || health.current >= health.max {
+            stats.regen_progress = 0.0;
+            continue;
+        }
+        stats.regen_progress += stats.get(Stat::Regen) * time.delta_seconds();
+        let healed = stats.regen_progress.floor();
+        if healed >= 1.0 {
+            stats.regen_progress -= healed;
+            health.current = (health.current + healed as u32).min(health.max);
+        }
+    }
+}
+
+/// Puts hit entities under the status effect of the damage that hit them.
+fn inflict_status_effects(
+    mut damage_dealt: EventReader<DamageDealt>,
+    mut targets: Query<(&mut Stats, &mut StatusEffects)>,
+) {
+    for hit in damage_dealt.read() {
+        let Some(effect) = StatusEffect::inflicted_by(hit.damage_type) else {
+            continue;
+        };
+        let Ok((mut stats, mut effects)) = targets.get_mut(hit.target) else {
+            continue;
+        };
+        let timer = Timer::from_seconds(effect.duration(), TimerMode::Once);
+        // Only a new effect changes stats; a repeat hit just restarts it.
+        if effects.0.insert(effect, timer).is_none() {
+            stats.set_modifiers(ModifierSource::StatusEffect(effect), effect.modifiers());
+        }
+    }
+}
+
+fn expire_status_effects(mut targets: Query<(&mut Stats, &mut StatusEffects)>, time: Res<Time>) {
+    for (mut stats, mut effects) in targets.iter_mut() {
+        let mut expired = Vec::new();
+        effects.0.retain(|&effect, timer| {
+            timer.tick(time.delta());
+            if timer.finished() {
+                expired.push(effect);
+            }
+            !timer.finished()
+        });
+        for effect in expired {
+            stats.remove_modifiers(&ModifierSource::StatusEffect(effect));
+        }
+    }
+}
@@ -0,0 +1,500 @@
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashSet};
+use rand::{seq::SliceRandom, thread_rng, Rng};
+use serde::Deserialize;
+
+use crate::{
+    evolution::DiscoveredEvolutions,
+    menu::{spawn_menu, MenuSelected},
+    passives::{PassiveDefinition, PassiveItem, PassiveItems, MAX_PASSIVES},
+    pause::{pause, resume, PlayState},
+    ron_loader::RonAssetApp,
+    stats::{Stat, Stats},
+    weapons::{Weapon, WeaponDefinition, Weapons},
+    GameState, Player,
+};
+
+/// Offers a choice of upgrades every time the player levels up.
+pub struct UpgradesPlugin;
+impl Plugin for UpgradesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_ron_asset::<UpgradePool>(&["pool.ron"]);
+        app.init_resource::<LevelUpCharges>();
+        app.add_systems(Startup, load_pool);
+        app.add_systems(Update, load_pool_contents);
+        app.add_systems(OnEnter(GameState::Playing), reset_charges);
+        app.add_systems(OnEnter(PlayState::LevelUp), (pause, roll_offers));
+        app.add_systems(OnExit(PlayState::LevelUp), (resume, teardown_level_up_menu));
+        app.add_systems(
+            Update,
+            level_up_selected.run_if(in_state(PlayState::LevelUp)),
+        );
+        app.add_systems(
+            Update,
+            refresh_level_up_menu
+                .after(level_up_selected)
+                .run_if(in_state(PlayState::LevelUp))
+                .run_if(resource_exists_and_changed::<LevelUpOffers>),
+        );
+    }
+}
+
+/// How often an upgrade turns up in level-up offers.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Legendary,
+}
+
+impl Rarity {
+    fn weight(self) -> f32 {
+        match self {
+            Rarity::Common => 100.0,
+            Rarity::Uncommon => 50.0,
+            Rarity::Rare => 20.0,
+            Rarity::Legendary => 5.0,
+        }
+    }
+}
+
+/// Everything that can be offered on level up, loaded from a `.pool.ron` file.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct UpgradePool {
+    /// Asset paths of the weapons that can be picked up during a run.
+    pub weapons: Vec<String>,
+    /// Asset paths of the passive items that can be picked up during a run.
+    pub passives: Vec<String>,
+}
+
+#[derive(Resource)]
+struct Pool {
+    pool: Handle<UpgradePool>,
+    /// Loaded from the pool's paths once it has loaded.
+    weapons: Vec<Handle<WeaponDefinition>>,
+    passives: Vec<Handle<PassiveDefinition>>,
+}
+
+/// The pool and the definitions of everything in it.
+#[derive(SystemParam)]
+struct Catalogue<'w> {
+    pool: Res<'w, Pool>,
+    weapons: Res<'w, Assets<WeaponDefinition>>,
+    passives: Res<'w, Assets<PassiveDefinition>>,
+    /// Weapons that have evolved aren't offered again.
+    evolutions: Res<'w, DiscoveredEvolutions>,
+}
+
+/// Rerolls and banishes left this run, and what has been banished.
+#[derive(Resource)]
+struct LevelUpCharges {
+    rerolls: u32,
+    banishes: u32,
+    /// Weapon and passive item asset paths that won't be offered again this run.
+    banished: HashSet<String>,
+}
+
+impl Default for LevelUpCharges {
+    fn default() -> Self {
+        Self {
+            rerolls: 3,
+            banishes: 3,
+            banished: HashSet::new(),
+        }
+    }
+}
+
+/// The upgrades on offer, and whether the next pick banishes rather than takes one.
+#[derive(Resource, Default)]
+struct LevelUpOffers {
+    offers: Vec<Offer>,
+    banishing: bool,
+}
+
+/// Every offer is keyed by the asset path of its weapon or item.
+#[derive(Clone, Debug)]
+enum Offer {
+    /// Levels up the owned weapon at this index in [`Weapons`].
+    UpgradeWeapon { index: usize, key: String },
+    NewWeapon {
+        weapon: Handle<WeaponDefinition>,
+        key: String,
+    },
+    /// Levels up the carried item at this index in [`PassiveItems`].
+    UpgradePassive { index: usize, key: String },
+    NewPassive {
+        passive: Handle<PassiveDefinition>,
+        key: String,
+    },
+}
+
+impl Offer {
+    /// What banishing the offer removes from the pool.
+    fn key(&self) -> &str {
+        match self {
+            Offer::UpgradeWeapon { key, .. }
+            | Offer::NewWeapon { key, .. }
+            | Offer::UpgradePassive { key, .. }
+            | Offer::NewPassive { key, .. } => key,
+        }
+    }
+}
+
+#[derive(Component)]
+struct LevelUpMenu;
+
+const OFFER_COUNT: usize = 3;
+/// Chance of a fourth offer, scaled by luck.
+const BONUS_OFFER_CHANCE: f64 = 0.15;
+/// Upgrades to weapons already held are this much more likely to be offered.
+const OWNED_WEIGHT: f32 = 2.0;
+const MAX_WEAPONS: usize = 6;
+
+fn load_pool(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.insert_resource(Pool {
+        pool: assets.load("upgrades/base.pool.ron"),
+        weapons: Vec::new(),
+        passives: Vec::new(),
+    });
+}
+
+fn load_pool_contents(
+    mut pool: ResMut<Pool>,
+    pools: Res<Assets<UpgradePool>>,
+    assets: Res<AssetServer>,
+) {
+    if !pool.weapons.is_empty() || !pool.passives.is_empty() {
+        return;
+    }
+    if let Some(upgrade_pool) = pools.get(&pool.pool) {
+        pool.weapons = upgrade_pool
+            .weapons
+            .iter()
+            .map(|path| assets.load(path.clone()))
+            .collect();
+        pool.passives = upgrade_pool
+            .passives
+            .iter()
+            .map(|path| assets.load(path.clone()))
+            .collect();
+    }
+}
+
+/// Identifies an asset for banishing, falling back on its name if it wasn't loaded from a file.
+fn asset_key<A: Asset>(handle: &Handle<A>, name: &str) -> String {
+    handle
+        .path()
+        .map_or_else(|| name.to_string(), |path| path.to_string())
+}
+
+fn reset_charges(mut charges: ResMut<LevelUpCharges>) {
+    *charges = LevelUpCharges::default();
+}
+
+/// Every upgrade the player could be offered right now, with its weight.
+fn candidates(
+    catalogue: &Catalogue,
+    weapons: &Weapons,
+    passives: &PassiveItems,
+    charges: &LevelUpCharges,
+) -> Vec<(Offer, f32)> {
+    let mut candidates = Vec::new();
+    for (index, weapon) in weapons.0.iter().enumerate() {
+        let Some(definition) = catalogue.weapons.get(&weapon.definition) else {
+            continue;
+        };
+        let key = asset_key(&weapon.definition, &definition.name);
+        if weapon.level < definition.max_level && !charges.banished.contains(&key) {
+            candidates.push((
+                Offer::UpgradeWeapon { index, key },
+                definition.rarity.weight() * OWNED_WEIGHT,
+            ));
+        }
+    }
+    for (index, item) in passives.0.iter().enumerate() {
+        let Some(definition) = catalogue.passives.get(&item.definition) else {
+            continue;
+        };
+        let key = asset_key(&item.definition, &definition.name);
+        if item.level < definition.max_level && !charges.banished.contains(&key) {
+            candidates.push((
+                Offer::UpgradePassive { index, key },
+                definition.rarity.weight() * OWNED_WEIGHT,
+            ));
+        }
+    }
+    if weapons.0.len() < MAX_WEAPONS {
+        for handle in catalogue.pool.weapons.iter() {
+            let Some(definition) = catalogue.weapons.get(handle) else {
+                continue;
+            };
+            let key = asset_key(handle, &definition.name);
+            let owned = weapons
+                .0
+                .iter()
+                .any(|weapon| weapon.definition.id() == handle.id());
+            let evolved = catalogue.evolutions.0.contains_key(&key);
+            if !owned && !evolved && !charges.banished.contains(&key) {
+                candidates.push((
+                    Offer::NewWeapon {
+                        weapon: handle.clone(),
+                        key,
+                    },
+                    definition.rarity.weight(),
+                ));
+            }
+        }
+    }
+    if passives.0.len() < MAX_PASSIVES {
+        for handle in catalogue.pool.passives.iter() {
+            let Some(definition) = catalogue.passives.get(handle) else {
+                continue;
+            };
+            let key = asset_key(handle, &definition.name);
+            let owned = passives
+                .0
+                .iter()
+                .any(|item| item.definition.id() == handle.id());
+            if !owned && !charges.banished.contains(&key) {
+                candidates.push((
+                    Offer::NewPassive {
+                        passive: handle.clone(),
+                        key,
+                    },
+                    definition.rarity.weight(),
+                ));
+            }
+        }
+    }
+    candidates
+}
+
+/// Picks new offers, weighted by rarity and by what the player already holds.
+fn roll_offers(
+    mut commands: Commands,
+    player: Query<(&Weapons, &PassiveItems, &Stats), With<Player>>,
+    catalogue: Catalogue,
+    charges: Res<LevelUpCharges>,
+) {
+    let offers = player
+        .get_single()
+        .map_or(Vec::new(), |(weapons, passives, stats)| {
+            pick(
+                candidates(&catalogue, weapons, passives, &charges),
+                offer_count(stats),
+            )
+        });
+    commands.insert_resource(LevelUpOffers {
+        offers,
+        banishing: false,
+    });
+}
+
+fn offer_count(stats: &Stats) -> usize {
+    let chance = BONUS_OFFER_CHANCE * stats.get(Stat::Luck) as f64;
+    if thread_rng().gen_bool(chance.clamp(0.0, 1.0)) {
+        OFFER_COUNT + 1
+    } else {
+        OFFER_COUNT
+    }
+}
+
+/// Chooses up to `count` different offers by weight.
+fn pick(candidates: Vec<(Offer, f32)>, count: usize) -> Vec<Offer> {
+    candidates
+        .choose_multiple_weighted(&mut thread_rng(), count, |(_, weight)| *weight)
+        .map_or(Vec::new(), |chosen| {
+            chosen.map(|(offer, _)| offer.clone()).collect()
+        })
+}
+
+fn offer_label(
+    offer: &Offer,
+    weapons: &Weapons,
+    passives: &PassiveItems,
+    catalogue: &Catalogue,
+) -> String {
+    match offer {
+        Offer::UpgradeWeapon { index, .. } => {
+            let weapon = &weapons.0[*index];
+            let name = catalogue
+                .weapons
+                .get(&weapon.definition)
+                .map_or("Weapon", |definition| &definition.name);
+            format!("{name} Lv {} > {}", weapon.level, weapon.level + 1)
+        }
+        Offer::NewWeapon { weapon, .. } => {
+            let name = catalogue
+                .weapons
+                .get(weapon)
+                .map_or("Weapon", |definition| &definition.name);
+            format!("New weapon: {name}")
+        }
+        Offer::UpgradePassive { index, .. } => {
+            let item = &passives.0[*index];
+            let name = catalogue
+                .passives
+                .get(&item.definition)
+                .map_or("Passive", |definition| &definition.name);
+            format!("{name} Lv {} > {}", item.level, item.level + 1)
+        }
+        Offer::NewPassive { passive, .. } => {
+            let name = catalogue
+                .passives
+                .get(passive)
+                .map_or("Passive", |definition| &definition.name);
+            format!("New passive: {name}")
+        }
+    }
+}
+
+/// Spawns the menu whenever the offers change, replacing the last one.
+fn refresh_level_up_menu(
+    mut commands: Commands,
+    menus: Query<Entity, With<LevelUpMenu>>,
+    offers: Res<LevelUpOffers>,
+    charges: Res<LevelUpCharges>,
+    player: Query<(&Player, &Weapons, &PassiveItems)>,
+    catalogue: Catalogue,
+) {
+    for menu in menus.iter() {
+        commands.entity(menu).despawn_recursive();
+    }
+    let Ok((player, weapons, passives)) = player.get_single() else {
+        return;
+    };
+    let mut options: Vec<_> = offers
+        .offers
+        .iter()
+        .map(|offer| offer_label(offer, weapons, passives, &catalogue))
+        .collect();
+    let (title, lines) = if offers.banishing {
+        options.push("Cancel".to_string());
+        (
+            "Banish".to_string(),
+            vec!["Pick an upgrade to never see again this run".to_string()],
+        )
+    } else {
+        options.push(format!("Reroll ({})", charges.rerolls));
+        options.push(format!("Banish ({})", charges.banishes));
+        options.push("Skip".to_string());
+        // Levels still waiting are chosen for in order.
+        (
+            format!("Level {}", player.level + 1 - player.pending_level_ups),
+            Vec::new(),
+        )
+    };
+    let options: Vec<_> = options.iter().map(String::as_str).collect();
+    let menu = spawn_menu(&mut commands, &title, &lines, &options);
+    commands.entity(menu).insert(LevelUpMenu);
+}
+
+fn teardown_level_up_menu(mut commands: Commands, menus: Query<Entity, With<LevelUpMenu>>) {
+    for menu in menus.iter() {
+        commands.entity(menu).despawn_recursive();
+    }
+}
+
+fn level_up_selected(
+    mut selected: EventReader<MenuSelected>,
+    menus: Query<(), With<LevelUpMenu>>,
+    mut offers: ResMut<LevelUpOffers>,
+    mut charges: ResMut<LevelUpCharges>,
+    mut player: Query<(&mut Player, &mut Weapons, &mut PassiveItems, &Stats)>,
+    mut next_state: ResMut<NextState<PlayState>>,
+    catalogue: Catalogue,
+) {
+    for selected in selected.read() {
+        if !menus.contains(selected.menu) {
+            continue;
+        }
+        let Ok((mut player, mut weapons, mut passives, stats)) = player.get_single_mut() else {
+            continue;
+        };
+        let option = selected.option;
+        // Whether this level's upgrade has been taken or skipped.
+        let mut done = false;
+        if let Some(offer) = offers.offers.get(option).cloned() {
+            if offers.banishing {
+                charges.banishes -= 1;
+                charges.banished.insert(offer.key().to_string());
+                offers.banishing = false;
+                // Replace the banished offer with one that isn't already showing.
+                offers.offers.remove(option);
+                let showing: HashSet<_> = offers
+                    .offers
+                    .iter()
+                    .map(|offer| offer.key().to_string())
+                    .collect();
+                let replacement = candidates(&catalogue, &weapons, &passives, &charges)
+                    .into_iter()
+                    .filter(|(offer, _)| !showing.contains(offer.key()))
+                    .collect::<Vec<_>>()
+                    .choose_weighted(&mut thread_rng(), |(_, weight)| *weight)
+                    .ok()
+                    .map(|(offer, _)| offer.clone());
+                if let Some(replacement) = replacement {
+                    offers.offers.insert(option, replacement);
+                }
+            } else {
+                match offer {
+                    Offer::UpgradeWeapon { index, .. } => {
+                        let weapon = &mut weapons.0[index];
+                        let max_level = catalogue
+                            .weapons
+                            .get(&weapon.definition)
+                            .map_or(weapon.level, |definition| definition.max_level);
+                        weapon.level = (weapon.level + 1).min(max_level);
+                    }
+                    Offer::NewWeapon { weapon, .. } => weapons.0.push(Weapon::new(weapon)),
+                    Offer::UpgradePassive { index, .. } => {
+                        let item = &mut passives.0[index];
+                        let max_level = catalogue
+                            .passives
+                            .get(&item.definition)
+                            .map_or(item.level, |definition| definition.max_level);
+                        item.level = (item.level + 1).min(max_level);
+                    }
+                    Offer::NewPassive { passive, .. } => {
+                        passives.0.push(PassiveItem::new(passive));
+                    }
+                }
+                done = true;
+            }
+        } else if offers.banishing {
+            // Cancel
+            offers.banishing = false;
+        } else {
+            match option - offers.offers.len() {
+                0 if charges.rerolls > 0 => {
+                    charges.rerolls -= 1;
+                    offers.offers = pick(
+                        candidates(&catalogue, &weapons, &passives, &charges),
+                        offers.offers.len(),
+                    );
+                }
+                1 if charges.banishes > 0 && !offers.offers.is_empty() => {
+                    offers.banishing = true;
+                }
+                2 => done = true,
+                // Out of rerolls or banishes.
+                _ => continue,
+            }
+        }
+        if done {
+            player.pending_level_ups = player.pending_level_ups.saturating_sub(1);
+            if player.pending_level_ups == 0 {
+                next_state.set(PlayState::Running);
+                continue;
+            }
+            // Straight on to the next level's upgrade.
+            offers.offers = pick(
+                candidates(&catalogue, &weapons, &passives, &charges),
+                offer_count(stats),
+            );
+        }
+        // Rebuild the menu to show the new offers and charges.
+        offers.set_changed();
+    }
+}
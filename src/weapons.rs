@@ -0,0 +1,752 @@
+use std::{
+    f32::consts::{FRAC_PI_2, TAU},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_rapier2d::{
+    geometry::{Collider, CollisionGroups},
+    pipeline::QueryFilter,
+    plugin::RapierContext,
+};
+use bevy_spritesheet_animation::library::SpritesheetLibrary;
+use rand::{thread_rng, Rng};
+use serde::Deserialize;
+
+use crate::{
+    damage::{DamageEvent, DamageRoll, DamageType},
+    enemies::Enemy,
+    projectiles::{spawn_projectile, Homing, OnHit},
+    ron_loader::RonAssetApp,
+    run::RunEntity,
+    stats::{Stat, Stats},
+    upgrades::Rarity,
+    Dead, GameState, Health, Player, ENEMY_GROUP, PROJECTILE_GROUP,
+};
+
+pub struct WeaponsPlugin;
+impl Plugin for WeaponsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_ron_asset::<WeaponDefinition>(&["weapon.ron"]);
+        app.add_event::<WeaponFired>();
+        app.add_systems(Update, fire_weapons.run_if(in_state(GameState::Playing)));
+        app.add_systems(
+            Update,
+            equip_weapon_effects.run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(
+            Update,
+            refresh_weapon_effects
+                .before(equip_weapon_effects)
+                .run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(Update, orbit.run_if(in_state(GameState::Playing)));
+        app.add_systems(Update, fade_lines.run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// Sent each time one of the player's weapons fires.
+#[derive(Event)]
+pub struct WeaponFired {
+    /// Unit vector the weapon fired along.
+    pub direction: Vec2,
+}
+
+/// Everything needed to fire a weapon, loaded from a `.weapon.ron` file.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct WeaponDefinition {
+    pub name: String,
+    pub kind: WeaponKind,
+    /// Seconds between volleys at level 1.
+    pub cooldown: f32,
+    /// Multiplier applied to the cooldown for every level past the first.
+    pub cooldown_per_level: f32,
+    /// Level at which the weapon stops improving and can evolve.
+    pub max_level: u32,
+    /// How often the weapon is offered on level up.
+    #[serde(default)]
+    pub rarity: Rarity,
+    pub damage: u32,
+    /// Damage added for every level past the first.
+    #[serde(default)]
+    pub damage_per_level: u32,
+    /// Crit and variance bonuses added to the player's own.
+    #[serde(default)]
+    pub damage_roll: DamageRoll,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    pub projectile_count: u32,
+    /// Projectiles added for every level past the first, rounded down.
+    #[serde(default)]
+    pub projectile_count_per_level: f32,
+    /// Total angle in degrees that a volley is fanned across.
+    pub spread: f32,
+    /// Projectile speed in pixels per second.
+    pub speed: f32,
+    /// Seconds a projectile lives before despawning.
+    pub lifespan: f32,
+    pub on_hit: OnHit,
+    /// How many times a projectile bounces off terrain before despawning.
+    pub ricochet: u32,
+    /// Seconds before a projectile may hit the same enemy again; never if unset.
+    pub hit_cooldown: Option<f32>,
+    /// Scale applied to the projectile sprite and collider.
+    pub area: f32,
+    /// Added to `area` for every level past the first.
+    #[serde(default)]
+    pub area_per_level: f32,
+    pub targeting: Targeting,
+    /// Degrees per second a projectile turns toward its target; flies straight if unset.
+    pub homing: Option<f32>,
+    /// Chain and beam weapons draw lines instead of spawning projectiles.
+    #[serde(default)]
+    pub sprite: Option<ProjectileSprite>,
+}
+
+/// How a weapon delivers its damage.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum WeaponKind {
+    /// Fires volleys of projectiles.
+    Projectile,
+    /// A field around the player that damages everything inside it.
+    Aura,
+    /// Blades that circle the player, `speed` pixels per second along the circle.
+    Orbit { radius: f32 },
+    /// Patches of ground dropped on targets that damage anything standing in them.
+    Zone,
+    /// Strikes an enemy within `range` instantly, then jumps to up to `jumps` more enemies
+    /// within `jump_range` of the last, keeping `falloff` of its damage each time.
+    Chain {
+        range: f32,
+        jumps: u32,
+        jump_range: f32,
+        falloff: f32,
+        line: LineStyle,
+    },
+    /// Damages every enemy along a line `length` pixels long each time it fires.
+    Beam { length: f32, line: LineStyle },
+}
+
+/// How the short-lived line drawn by chain and beam weapons looks.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct LineStyle {
+    pub colour: Color,
+    pub width: f32,
+    /// Seconds the line takes to fade out.
+    pub duration: f32,
+}
+
+/// How a weapon picks the direction of each volley.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum Targeting {
+    /// Fires wherever the player is aiming.
+    Facing,
+    /// Fires at the closest enemy within range.
+    Nearest { range: f32 },
+    /// Fires at the most damaged enemy within range.
+    LowestHealth { range: f32 },
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ProjectileSprite {
+    pub texture: String,
+    pub tile_size: UVec2,
+    pub columns: u32,
+    pub rows: u32,
+    pub row: usize,
+    pub frames: usize,
+    /// Milliseconds per animation cycle.
+    pub duration: u32,
+    pub size: Vec2,
+    pub collider: Vec2,
+}
+
+impl WeaponDefinition {
+    pub fn cooldown_at(&self, level: u32) -> f32 {
+        self.cooldown * self.cooldown_per_level.powi(level.saturating_sub(1) as i32)
+    }
+
+    pub fn damage_at(&self, level: u32) -> u32 {
+        self.damage + self.damage_per_level * level.saturating_sub(1)
+    }
+
+    pub fn area_at(&self, level: u32) -> f32 {
+        self.area + self.area_per_level * level.saturating_sub(1) as f32
+    }
+
+    pub fn projectile_count_at(&self, level: u32) -> u32 {
+        self.projectile_count
+            + (self.projectile_count_per_level * level.saturating_sub(1) as f32) as u32
+    }
+}
+
+/// An equipped weapon, ticking its own cooldown.
+pub struct Weapon {
+    pub definition: Handle<WeaponDefinition>,
+    pub cooldown: Timer,
+    pub level: u32,
+    /// Entities that last as long as the weapon is equipped, like auras and orbiting blades.
+    pub effects: Vec<Entity>,
+    /// What the weapon's effects were spawned with.
+    effects_spawned_with: EffectInputs,
+}
+
+/// Everything the size and number of a weapon's effects depend on, so they are only respawned
+/// when one of these changes rather than on every change to the player's stats.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct EffectInputs {
+    level: u32,
+    area: f32,
+    projectile_count: f32,
+}
+
+impl EffectInputs {
+    fn new(level: u32, stats: &Stats) -> Self {
+        Self {
+            level,
+            area: stats.get(Stat::Area),
+            projectile_count: stats.get(Stat::ProjectileCount),
+        }
+    }
+}
+
+impl Weapon {
+    pub fn new(definition: Handle<WeaponDefinition>) -> Self {
+        Self {
+            definition,
+            cooldown: Timer::new(Duration::ZERO, TimerMode::Repeating),
+            level: 1,
+            effects: Vec::new(),
+            effects_spawned_with: EffectInputs::default(),
+        }
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Weapons(pub Vec<Weapon>);
+
+/// How far extra zones in a volley land from the first.
+const ZONE_SCATTER: f32 = 64.0;
+/// Zones sit on the ground, under the player and enemies.
+const ZONE_Z: f32 = 0.5;
+/// Chain and beam lines are drawn over everything else in the world.
+const LINE_Z: f32 = 10.0;
+
+/// Picks the enemy a volley should be aimed at, if the weapon aims at all.
+fn find_target(
+    targeting: Targeting,
+    origin: Vec3,
+    enemies: &Query<(Entity, &Transform, &Health), (With<Enemy>, Without<Dead>, Without<Player>)>,
+) -> Option<(Entity, Vec3)> {
+    let in_range = |range: f32| {
+        enemies
+            .iter()
+            .filter(move |(_, transform, _)| origin.distance(transform.translation) <= range)
+    };
+    let (entity, transform, _) = match targeting {
+        Targeting::Facing => return None,
+        Targeting::Nearest { range } => in_range(range).min_by(|(_, a, _), (_, b, _)| {
+            origin
+                .distance(a.translation)
+                .total_cmp(&origin.distance(b.translation))
+        })?,
+        Targeting::LowestHealth { range } => {
+            in_range(range).min_by_key(|(_, _, health)| health.current)?
+        }
+    };
+    Some((entity, transform.translation))
+}
+
+/// Keeps an orbiting blade circling its parent.
+#[derive(Component)]
+struct Orbiting {
+    /// Current angle around the parent in radians.
+    angle: f32,
+    radius: f32,
+    /// Radians per second.
+    speed: f32,
+}
+
+fn orbit(mut blades: Query<(&mut Orbiting, &mut Transform)>, time: Res<Time>) {
+    for (mut orbiting, mut transform) in blades.iter_mut() {
+        orbiting.angle = (orbiting.angle + orbiting.speed * time.delta_seconds()) % TAU;
+        let offset = Vec2::from_angle(orbiting.angle) * orbiting.radius;
+        transform.translation = offset.extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(orbiting.angle + FRAC_PI_2);
+    }
+}
+
+/// Removes equipped weapon effects when the weapon's level or the stats they are sized by
+/// change, so they are respawned to match.
+fn refresh_weapon_effects(mut commands: Commands, mut player: Query<(&mut Weapons, &Stats)>) {
+    for (mut weapons, stats) in player.iter_mut() {
+        for weapon in weapons.0.iter_mut() {
+            if weapon.effects_spawned_with == EffectInputs::new(weapon.level, stats) {
+                continue;
+            }
+            for effect in weapon.effects.drain(..) {
+                commands.entity(effect).despawn_recursive();
+            }
+        }
+    }
+}
+
+/// Volleys grow with the weapon's level and the player's projectile count stat.
+fn projectile_count(definition: &WeaponDefinition, level: u32, stats: &Stats) -> u32 {
+    definition.projectile_count_at(level).max(1) + stats.get(Stat::ProjectileCount).max(0.0) as u32
+}
+
+/// Spawns the entities of weapons that stay with the player rather than firing.
+fn equip_weapon_effects(
+    mut commands: Commands,
+    mut player: Query<(Entity, &mut Weapons, &Stats)>,
+    definitions: Res<Assets<WeaponDefinition>>,
+    assets: Res<AssetServer>,
+    mut library: ResMut<SpritesheetLibrary>,
+    mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    for (player, mut weapons, stats) in player.iter_mut() {
+        let area = stats.get(Stat::Area);
+        for weapon in weapons.0.iter_mut() {
+            if !weapon.effects.is_empty() {
+                continue;
+            }
+            let Some(definition) = definitions.get(&weapon.definition) else {
+                continue;
+            };
+            let Some(sprite) = &definition.sprite else {
+                continue;
+            };
+            weapon.effects_spawned_with = EffectInputs::new(weapon.level, stats);
+            match definition.kind {
+                WeaponKind::Aura => {
+                    let aura = spawn_projectile(
+                        &mut commands,
+                        &assets,
+                        &mut library,
+                        &mut atlas_layouts,
+                        definition,
+                        weapon.level,
+                        sprite,
+                        Transform::from_xyz(0.0, 0.0, -1.0).with_scale(Vec3::splat(area)),
+                    );
+                    commands.entity(aura).set_parent(player);
+                    weapon.effects.push(aura);
+                }
+                WeaponKind::Orbit { radius } => {
+                    let count = projectile_count(definition, weapon.level, stats);
+                    let radius = radius * definition.area_at(weapon.level) * area;
+                    for i in 0..count {
+                        let blade = spawn_projectile(
+                            &mut commands,
+                            &assets,
+                            &mut library,
+                            &mut atlas_layouts,
+                            definition,
+                            weapon.level,
+                            sprite,
+                            Transform::from_xyz(0.0, 0.0, 1.0).with_scale(Vec3::splat(area)),
+                        );
+                        commands
+                            .entity(blade)
+                            .insert(Orbiting {
+                                angle: TAU * i as f32 / count as f32,
+                                radius,
+                                speed: definition.speed / radius,
+                            })
+                            .set_parent(player);
+                        weapon.effects.push(blade);
+                    }
+                }
+                WeaponKind::Projectile
+                | WeaponKind::Zone
+                | WeaponKind::Chain { .. }
+                | WeaponKind::Beam { .. } => {}
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn fire_weapons(
+    mut commands: Commands,
+    mut player: Query<(
+        Entity,
+        &Player,
+        &mut Weapons,
+        &Transform,
+        &DamageRoll,
+        &Stats,
+    )>,
+    enemies: Query<(Entity, &Transform, &Health), (With<Enemy>, Without<Dead>, Without<Player>)>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut weapon_fired: EventWriter<WeaponFired>,
+    definitions: Res<Assets<WeaponDefinition>>,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>,
+    assets: Res<AssetServer>,
+    mut library: ResMut<SpritesheetLibrary>,
+    mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    for (player_entity, player, mut weapons, transform, player_roll, stats) in player.iter_mut() {
+        let area = stats.get(Stat::Area);
+        // Weapons always take some time to fire again.
+        let cooldown_multiplier = 1.0 - stats.get(Stat::CooldownReduction).clamp(0.0, 0.9);
+        for weapon in weapons.0.iter_mut() {
+            let Some(definition) = definitions.get(&weapon.definition) else {
+                continue;
+            };
+            if let WeaponKind::Aura | WeaponKind::Orbit { .. } = definition.kind {
+                continue;
+            }
+            weapon.cooldown.set_duration(Duration::from_secs_f32(
+                definition.cooldown_at(weapon.level) * cooldown_multiplier,
+            ));
+            weapon.cooldown.tick(time.delta());
+            if !weapon.cooldown.just_finished() {
+                continue;
+            }
+            let target = find_target(definition.targeting, transform.translation, &enemies);
+            let facing = match target {
+                // Facing is measured so that 270 degrees on from it points along +X.
+                Some((_, target)) => {
+                    (target - transform.translation)
+                        .truncate()
+                        .to_angle()
+                        .to_degrees()
+                        - 270.0
+                }
+                None => player.facing,
+            };
+            let direction = Vec2::from_angle((facing + 270.0).to_radians());
+            weapon_fired.send(WeaponFired { direction });
+            match definition.kind {
+                WeaponKind::Chain { .. } => {
+                    fire_chain(
+                        &mut commands,
+                        &mut damage_events,
+                        &rapier_context,
+                        &enemies,
+                        definition,
+                        weapon.level,
+                        player_roll.combine(definition.damage_roll),
+                        player_entity,
+                        transform.translation,
+                        direction,
+                        target.map(|(entity, _)| entity),
+                    );
+                    continue;
+                }
+                WeaponKind::Beam { .. } => {
+                    fire_beam(
+                        &mut commands,
+                        &mut damage_events,
+                        &rapier_context,
+                        &enemies,
+                        definition,
+                        weapon.level,
+                        player_roll.combine(definition.damage_roll),
+                        player_entity,
+                        transform.translation,
+                        direction,
+                        area,
+                    );
+                    continue;
+                }
+                _ => {}
+            }
+            let Some(sprite) = &definition.sprite else {
+                continue;
+            };
+            let count = projectile_count(definition, weapon.level, stats);
+            if let WeaponKind::Zone = definition.kind {
+                let centre = target.map_or(transform.translation, |(_, target)| target);
+                for i in 0..count {
+                    // Only the first zone lands squarely on the target.
+                    let offset = if i == 0 {
+                        Vec2::ZERO
+                    } else {
+                        Vec2::from_angle(thread_rng().gen_range(0.0..TAU))
+                            * thread_rng().gen_range(0.0..ZONE_SCATTER)
+                    };
+                    spawn_projectile(
+                        &mut commands,
+                        &assets,
+                        &mut library,
+                        &mut atlas_layouts,
+                        definition,
+                        weapon.level,
+                        sprite,
+                        Transform::from_translation((centre.truncate() + offset).extend(ZONE_Z))
+                            .with_scale(Vec3::splat(area)),
+                    );
+                }
+                continue;
+            }
+            let (start, step) = if count > 1 {
+                (
+                    facing - definition.spread / 2.0,
+                    definition.spread / (count - 1) as f32,
+                )
+            } else {
+                (facing, 0.0)
+            };
+            for i in 0..count {
+                let angle = start + step * i as f32;
+                let rotation =
+                    Quat::from_axis_angle(Vec3::Z, ((angle + 270.0) % 360.0).to_radians());
+                let projectile = spawn_projectile(
+                    &mut commands,
+                    &assets,
+                    &mut library,
+                    &mut atlas_layouts,
+                    definition,
+                    weapon.level,
+                    sprite,
+                    Transform::from_translation(transform.translation + Vec3::Z)
+                        .with_rotation(rotation)
+                        .with_scale(Vec3::splat(area)),
+                );
+                if let Some(turn_rate) = definition.homing {
+                    commands.entity(projectile).insert(Homing {
+                        target: target.map(|(entity, _)| entity),
+                        turn_rate,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Only enemies are struck by chains and beams.
+fn enemy_filter<'a>() -> QueryFilter<'a> {
+    QueryFilter::new().groups(CollisionGroups::new(PROJECTILE_GROUP, ENEMY_GROUP))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn fire_chain(
+    commands: &mut Commands,
+    damage_events: &mut EventWriter<DamageEvent>,
+    rapier_context: &RapierContext,
+    enemies: &Query<(Entity, &Transform, &Health), (With<Enemy>, Without<Dead>, Without<Player>)>,
+    definition: &WeaponDefinition,
+    level: u32,
+    roll: DamageRoll,
+    source: Entity,
+    origin: Vec3,
+    direction: Vec2,
+    target: Option<Entity>,
+) {
+    let WeaponKind::Chain {
+        range,
+        jumps,
+        jump_range,
+        falloff,
+        line,
+    } = definition.kind
+    else {
+        return;
+    };
+    // Without a target to lock on to, the chain starts with whatever is straight ahead.
+    let Some(mut current) = target.or_else(|| {
+        rapier_context
+            .cast_ray(origin.truncate(), direction, range, true, enemy_filter())
+            .map(|(entity, _)| entity)
+    }) else {
+        return;
+    };
+    let mut from = origin;
+    let mut damage = definition.damage_at(level) as f32;
+    let mut struck = Vec::new();
+    for _ in 0..=jumps {
+        let Ok((_, transform, _)) = enemies.get(current) else {
+            break;
+        };
+        let to = transform.translation;
+        damage_events.send(roll.event(
+            source,
+            current,
+            damage.round().max(1.0) as u32,
+            definition.damage_type,
+            true,
+        ));
+        spawn_line(commands, from, to, line);
+        struck.push(current);
+        from = to;
+        damage *= falloff;
+
+        let mut next = None;
+        let mut closest = f32::MAX;
+        rapier_context.intersections_with_shape(
+            to.truncate(),
+            0.0,
+            &Collider::ball(jump_range),
+            enemy_filter(),
+            |entity| {
+                if let Ok((_, transform, _)) = enemies.get(entity) {
+                    let distance = transform.translation.distance(to);
+                    if !struck.contains(&entity) && distance < closest {
+                        closest = distance;
+                        next = Some(entity);
+                    }
+                }
+                true
+            },
+        );
+        match next {
+            Some(next) => current = next,
+            None => break,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn fire_beam(
+    commands: &mut Commands,
+    damage_events: &mut EventWriter<DamageEvent>,
+    rapier_context: &RapierContext,
+    enemies: &Query<(Entity, &Transform, &Health), (With<Enemy>, Without<Dead>, Without<Player>)>,
+    definition: &WeaponDefinition,
+    level: u32,
+    roll: DamageRoll,
+    source: Entity,
+    origin: Vec3,
+    direction: Vec2,
+    area: f32,
+) {
+    let WeaponKind::Beam { length, line } = definition.kind else {
+        return;
+    };
+    let end = origin + (direction * length).extend(0.0);
+    let mut struck = Vec::new();
+    rapier_context.intersections_with_shape(
+        (origin.truncate() + end.truncate()) / 2.0,
+        direction.to_angle(),
+        &Collider::cuboid(
+            length / 2.0,
+            line.width * definition.area_at(level) * area / 2.0,
+        ),
+        enemy_filter(),
+        |entity| {
+            struck.push(entity);
+            true
+        },
+    );
+    for entity in struck {
+        if enemies.contains(entity) {
+            damage_events.send(roll.event(
+                source,
+                entity,
+                definition.damage_at(level),
+                definition.damage_type,
+                true,
+            ));
+        }
+    }
+    spawn_line(commands, origin, end, line);
+}
+
+/// A fading line left behind by a chain or beam.
+#[derive(Component)]
+struct LineEffect {
+    timer: Timer,
+    alpha: f32,
+}
+
+fn spawn_line(commands: &mut Commands, from: Vec3, to: Vec3, line: LineStyle) {
+    let delta = (to - from).truncate();
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: line.colour,
+                custom_size: Some(Vec2::new(delta.length(), line.width)),
+                ..default()
+            },
+            transform: Transform::from_translation(((from + to) / 2.0).truncate().extend(LINE_Z))
+                .with_rotation(Quat::from_rotation_z(delta.to_angle())),
+            ..default()
+        })
+        .insert(RunEntity)
+        .insert(LineEffect {
+            timer: Timer::from_seconds(line.duration, TimerMode::Once),
+            alpha: line.colour.alpha(),
+        });
+}
+
+fn fade_lines(
+    mut commands: Commands,
+    mut lines: Query<(Entity, &mut LineEffect, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut line, mut sprite) in lines.iter_mut() {
+        line.timer.tick(time.delta());
+        if line.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            sprite
+                .color
+                .set_alpha(line.alpha * line.timer.fraction_remaining());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::system::RunSystemOnce, utils::HashMap};
+
+    use super::*;
+    use crate::stats::{ModifierOp, ModifierSource, StatModifier, StatusEffect};
+
+    /// A player holding one weapon with a single spawned effect.
+    fn setup() -> (World, Entity, Entity) {
+        let mut world = World::new();
+        let effect = world.spawn_empty().id();
+        let stats = Stats::new(HashMap::new());
+        let mut weapon = Weapon::new(Handle::default());
+        weapon.effects.push(effect);
+        weapon.effects_spawned_with = EffectInputs::new(weapon.level, &stats);
+        let player = world.spawn((Weapons(vec![weapon]), stats)).id();
+        (world, player, effect)
+    }
+
+    fn modify(
+        world: &mut World,
+        player: Entity,
+        source: ModifierSource,
+        modifiers: Vec<StatModifier>,
+    ) {
+        world
+            .get_mut::<Stats>(player)
+            .unwrap()
+            .set_modifiers(source, modifiers);
+    }
+
+    #[test]
+    fn chill_keeps_weapon_effects() {
+        let (mut world, player, effect) = setup();
+        modify(
+            &mut world,
+            player,
+            ModifierSource::StatusEffect(StatusEffect::Chilled),
+            StatusEffect::Chilled.modifiers(),
+        );
+        world.run_system_once(refresh_weapon_effects);
+        assert!(world.get_entity(effect).is_some());
+    }
+
+    #[test]
+    fn area_change_respawns_weapon_effects() {
+        let (mut world, player, effect) = setup();
+        modify(
+            &mut world,
+            player,
+            ModifierSource::LevelUp,
+            vec![StatModifier {
+                stat: Stat::Area,
+                op: ModifierOp::Multiply(1.5),
+            }],
+        );
+        world.run_system_once(refresh_weapon_effects);
+        assert!(world.get_entity(effect).is_none());
+    }
+}